@@ -0,0 +1,306 @@
+//! Preprocessing over GF(2) for binary circuits
+//!
+//! Bits are XOR-shared between the parties and authenticated with MACs over
+//! GF(2^128): the MAC shares of a bit x XOR to x * Δ for the parties' shared
+//! binary MAC key Δ. Since x is a bit, x * Δ is either zero or the key itself,
+//! and the MACs are preserved by the XOR and public AND operations binary
+//! circuits are built from
+//!
+//! Bits are packed into 64-bit words and serialized, along with their MACs, as
+//! base64 encoded little-endian bytes
+
+use base64::prelude::*;
+use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
+
+/// An element of GF(2^128), used as a MAC key or MAC for binary values
+pub type BinaryMacKey = u128;
+
+/// The number of bits in a packed word
+const WORD_BITS: usize = u64::BITS as usize;
+
+// ---------------
+// | Packed Bits |
+// ---------------
+
+/// A vector of bits packed into 64-bit words
+///
+/// Bit `i` is held in bit `i % 64` of word `i / 64`, unused high bits of the
+/// last word are zero
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct PackedBits {
+    /// The packed words
+    words: Vec<u64>,
+    /// The number of bits
+    len: usize,
+}
+
+impl PackedBits {
+    /// Pack a sequence of bits
+    pub fn from_bits(bits: impl IntoIterator<Item = bool>) -> Self {
+        let mut packed = Self::default();
+        bits.into_iter().for_each(|bit| packed.push(bit));
+        packed
+    }
+
+    /// The number of bits
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether there are no bits
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the bit at the given index
+    pub fn get(&self, index: usize) -> bool {
+        assert!(index < self.len, "bit index out of bounds");
+        (self.words[index / WORD_BITS] >> (index % WORD_BITS)) & 1 == 1
+    }
+
+    /// Append a bit
+    pub fn push(&mut self, bit: bool) {
+        if self.len % WORD_BITS == 0 {
+            self.words.push(0);
+        }
+
+        *self.words.last_mut().unwrap() |= (bit as u64) << (self.len % WORD_BITS);
+        self.len += 1;
+    }
+
+    /// Iterate over the bits
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(|i| self.get(i))
+    }
+
+    /// The bitwise XOR of two vectors of the same length
+    pub fn xor(&self, other: &Self) -> Self {
+        self.zip_words(other, |a, b| a ^ b)
+    }
+
+    /// The bitwise AND of two vectors of the same length
+    pub fn and(&self, other: &Self) -> Self {
+        self.zip_words(other, |a, b| a & b)
+    }
+
+    /// The bits in the range `start..start + n`
+    pub fn slice(&self, start: usize, n: usize) -> Self {
+        Self::from_bits((start..start + n).map(|i| self.get(i)))
+    }
+
+    /// Append the bits of another vector
+    pub fn extend(&mut self, other: &Self) {
+        other.iter().for_each(|bit| self.push(bit));
+    }
+
+    /// Combine two vectors of the same length word by word
+    fn zip_words(&self, other: &Self, op: fn(u64, u64) -> u64) -> Self {
+        assert_eq!(self.len, other.len, "bit vectors differ in length");
+        let words = self.words.iter().zip(other.words.iter()).map(|(a, b)| op(*a, *b)).collect();
+        Self { words, len: self.len }
+    }
+}
+
+/// The wire format of packed bits
+#[derive(Serialize, Deserialize)]
+struct PackedBitsWire {
+    /// The number of bits
+    len: usize,
+    /// The base64 encoded little-endian bytes of the packed words
+    words: String,
+}
+
+impl Serialize for PackedBits {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = self.words.iter().flat_map(|w| w.to_le_bytes()).collect::<Vec<_>>();
+        PackedBitsWire { len: self.len, words: BASE64_STANDARD.encode(bytes) }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PackedBits {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let wire = PackedBitsWire::deserialize(deserializer)?;
+        let words: Vec<u64> = decode_le(&wire.words).map_err(DeError::custom)?;
+        if words.len() != wire.len.div_ceil(WORD_BITS) {
+            return Err(DeError::custom("packed bit length mismatch"));
+        }
+
+        Ok(Self { words, len: wire.len })
+    }
+}
+
+// ----------------------
+// | Authenticated Bits |
+// ----------------------
+
+/// A party's authenticated shares of a vector of bits
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct BinaryShares {
+    /// The party's XOR shares of the bits
+    pub bits: PackedBits,
+    /// The party's XOR shares of the MAC of each bit
+    #[serde(serialize_with = "serialize_blocks", deserialize_with = "deserialize_blocks")]
+    pub macs: Vec<BinaryMacKey>,
+}
+
+impl BinaryShares {
+    /// The number of shared bits
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Whether there are no shared bits
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    /// The shares in the range `start..start + n`
+    pub fn slice(&self, start: usize, n: usize) -> Self {
+        Self { bits: self.bits.slice(start, n), macs: self.macs[start..start + n].to_vec() }
+    }
+
+    /// Append the shares of another vector
+    pub fn extend(&mut self, other: &Self) {
+        self.bits.extend(&other.bits);
+        self.macs.extend_from_slice(&other.macs);
+    }
+}
+
+/// A party's shares of a batch of AND triples over GF(2)
+///
+/// Holds shares of random bit vectors a and b, and of c = a AND b
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct BinaryTriples {
+    /// The shares of the first operands
+    pub a: BinaryShares,
+    /// The shares of the second operands
+    pub b: BinaryShares,
+    /// The shares of the products
+    pub c: BinaryShares,
+}
+
+impl BinaryTriples {
+    /// The number of triples
+    pub fn len(&self) -> usize {
+        self.a.len()
+    }
+
+    /// Whether there are no triples
+    pub fn is_empty(&self) -> bool {
+        self.a.is_empty()
+    }
+
+    /// The triples in the range `start..start + n`
+    pub fn slice(&self, start: usize, n: usize) -> Self {
+        Self { a: self.a.slice(start, n), b: self.b.slice(start, n), c: self.c.slice(start, n) }
+    }
+
+    /// Append the triples of another batch
+    pub fn extend(&mut self, other: &Self) {
+        self.a.extend(&other.a);
+        self.b.extend(&other.b);
+        self.c.extend(&other.c);
+    }
+}
+
+// ---------------
+// | Wire Format |
+// ---------------
+
+/// Serialize a binary MAC key as base64 encoded little-endian bytes
+pub(crate) fn serialize_mac_key<S>(key: &BinaryMacKey, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&BASE64_STANDARD.encode(key.to_le_bytes()))
+}
+
+/// Deserialize a binary MAC key from base64 encoded little-endian bytes
+pub(crate) fn deserialize_mac_key<'de, D>(deserializer: D) -> Result<BinaryMacKey, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let encoded = String::deserialize(deserializer)?;
+    match decode_le::<BinaryMacKey>(&encoded).map_err(DeError::custom)?[..] {
+        [key] => Ok(key),
+        _ => Err(DeError::custom("expected a single MAC key")),
+    }
+}
+
+/// Serialize a vector of 128-bit blocks as base64 encoded little-endian bytes
+pub(crate) fn serialize_blocks<S>(blocks: &[u128], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let bytes = blocks.iter().flat_map(|b| b.to_le_bytes()).collect::<Vec<_>>();
+    serializer.serialize_str(&BASE64_STANDARD.encode(bytes))
+}
+
+/// Deserialize a vector of 128-bit blocks from base64 encoded little-endian
+/// bytes
+pub(crate) fn deserialize_blocks<'de, D>(deserializer: D) -> Result<Vec<u128>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let encoded = String::deserialize(deserializer)?;
+    decode_le(&encoded).map_err(DeError::custom)
+}
+
+/// Decode base64 encoded little-endian bytes into a vector of integers
+fn decode_le<T: FromLeBytes>(encoded: &str) -> Result<Vec<T>, String> {
+    let bytes = BASE64_STANDARD.decode(encoded).map_err(|e| e.to_string())?;
+    if bytes.len() % T::N_BYTES != 0 {
+        return Err("invalid encoded length".to_string());
+    }
+
+    Ok(bytes.chunks_exact(T::N_BYTES).map(T::from_le_slice).collect())
+}
+
+/// An integer decoded from little-endian bytes
+trait FromLeBytes {
+    /// The number of bytes in the integer
+    const N_BYTES: usize;
+    /// Decode the integer from a slice of exactly `N_BYTES` bytes
+    fn from_le_slice(bytes: &[u8]) -> Self;
+}
+
+impl FromLeBytes for u64 {
+    const N_BYTES: usize = 8;
+    fn from_le_slice(bytes: &[u8]) -> Self {
+        Self::from_le_bytes(bytes.try_into().unwrap())
+    }
+}
+
+impl FromLeBytes for u128 {
+    const N_BYTES: usize = 16;
+    fn from_le_slice(bytes: &[u8]) -> Self {
+        Self::from_le_bytes(bytes.try_into().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{thread_rng, Rng};
+
+    use super::{BinaryShares, PackedBits};
+
+    /// Tests packing bits across word boundaries and the wire encoding of
+    /// shares
+    #[test]
+    fn test_packed_encoding() {
+        let mut rng = thread_rng();
+        let bits = (0..130).map(|_| rng.gen_bool(0.5 /* p */)).collect::<Vec<_>>();
+        let packed = PackedBits::from_bits(bits.clone());
+        assert_eq!(packed.iter().collect::<Vec<_>>(), bits);
+        assert_eq!(packed.slice(60, 10).iter().collect::<Vec<_>>(), bits[60..70]);
+
+        let shares = BinaryShares { macs: (0..130).map(|_| rng.gen()).collect(), bits: packed };
+        let ser = serde_json::to_string(&shares).unwrap();
+        let de: BinaryShares = serde_json::from_str(&ser).unwrap();
+        assert!(de.bits == shares.bits && de.macs == shares.macs);
+
+        // A length inconsistent with the packed words is rejected
+        let bad = ser.replace("\"len\":130", "\"len\":500");
+        assert!(serde_json::from_str::<BinaryShares>(&bad).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+//! Seed-compressed dealing
+//!
+//! For two parties, the first party's shares of a batch may be derived from a
+//! short seed, so that only the second party needs its shares in full. The
+//! dealer sends the first party the seed, and the second party corrections:
+//! the values and MACs less the first party's expanded shares
+//!
+//! Compression applies to random bits, random values, and Beaver triples. The
+//! seed is expanded as follows, so that clients may implement it elsewhere:
+//! - The PRG is ChaCha20, i.e. `rand_chacha`'s `ChaCha20Rng` seeded directly
+//!   with the 32-byte seed
+//! - Each scalar is drawn from 64 bytes of the stream, interpreted as a
+//!   big-endian integer and reduced modulo the scalar field's order
+//! - For each value, the share is drawn before its MAC
+//! - Sections are expanded in order: the random bits, the random values, and
+//!   the a, b, and c values of the triples
+
+use rand_chacha::{
+    rand_core::{RngCore, SeedableRng},
+    ChaCha20Rng,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{Scalar, ScalarShare};
+
+/// A seed from which a party's shares are expanded
+pub type Seed = [u8; 32];
+
+/// The number of bytes of the stream drawn for each scalar
+///
+/// Twice the size of a scalar, so that the bias of the reduction is
+/// negligible
+const BYTES_PER_SCALAR: usize = 64;
+
+/// A party's shares compressed into a seed
+///
+/// Holds only the random bits, random values, and Beaver triples under the
+/// global MAC key; the other sections of a response are sent alongside it
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct CompressedShares {
+    /// The seed from which the shares are expanded
+    pub seed: Seed,
+    /// The number of random bits
+    pub n_random_bits: u32,
+    /// The number of random values
+    pub n_random_values: u32,
+    /// The number of Beaver triples
+    pub n_triples: u32,
+}
+
+/// A party's shares expanded from a seed
+#[derive(Clone, Default)]
+pub struct ExpandedShares {
+    /// The shares of the random bits
+    pub random_bits: Vec<ScalarShare>,
+    /// The shares of the random values
+    pub random_values: Vec<ScalarShare>,
+    /// The shares of the Beaver triples
+    pub beaver_triples: (Vec<ScalarShare>, Vec<ScalarShare>, Vec<ScalarShare>),
+}
+
+impl CompressedShares {
+    /// Expand the seed into the party's shares
+    pub fn expand(&self) -> ExpandedShares {
+        let mut prg = ChaCha20Rng::from_seed(self.seed);
+        let mut next_shares = |n: u32| {
+            (0..n)
+                .map(|_| {
+                    let share = next_scalar(&mut prg);
+                    let mac = next_scalar(&mut prg);
+                    ScalarShare::new(share, mac)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let random_bits = next_shares(self.n_random_bits);
+        let random_values = next_shares(self.n_random_values);
+        let a = next_shares(self.n_triples);
+        let b = next_shares(self.n_triples);
+        let c = next_shares(self.n_triples);
+
+        ExpandedShares { random_bits, random_values, beaver_triples: (a, b, c) }
+    }
+}
+
+/// Draw the next scalar from the PRG
+fn next_scalar(prg: &mut ChaCha20Rng) -> Scalar {
+    let mut bytes = [0u8; BYTES_PER_SCALAR];
+    prg.fill_bytes(&mut bytes);
+    Scalar::from_be_bytes_mod_order(&bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::CompressedShares;
+
+    /// Tests that expansion is deterministic in the seed and draws sections
+    /// from a single stream
+    #[test]
+    fn test_expansion() {
+        let compressed =
+            CompressedShares { seed: [7; 32], n_random_bits: 2, n_random_values: 3, n_triples: 4 };
+        let (first, second) = (compressed.expand(), compressed.expand());
+        assert_eq!(first.random_values, second.random_values);
+        assert_eq!(first.beaver_triples.2.len(), 4);
+
+        // The random values follow the random bits in the stream
+        let values_only = CompressedShares { n_random_bits: 0, ..compressed }.expand();
+        assert_eq!(values_only.random_values[..2], first.random_bits);
+        assert_eq!(values_only.random_values[2], first.random_values[0]);
+    }
+}
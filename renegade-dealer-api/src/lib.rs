@@ -9,7 +9,26 @@
 #![feature(generic_const_exprs)]
 #![feature(inherent_associated_types)]
 
+pub mod binary;
+pub mod compression;
+pub mod ot;
+pub mod pairwise;
+pub mod preprocessing;
+pub mod ring;
+pub mod ws;
+
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Formatter, Result as FmtResult},
+};
+
+use ark_mpc::{network::PartyId, PARTY0, PARTY1};
+use binary::{deserialize_mac_key, serialize_mac_key, BinaryMacKey, BinaryTriples};
+use compression::CompressedShares;
 use k256::PublicKey;
+use ot::OtResponse;
+use pairwise::{MacMode, PairwiseResponse};
+use ring::{RingResponse, MAX_RING_BIT_LENGTH};
 use serde::{Deserialize, Serialize};
 
 /// Serialize a public key
@@ -34,6 +53,16 @@ where
         .map_err(|e| DeError::custom(format!("Invalid public key bytes: {}", e)))
 }
 
+/// Whether a value is its type's default, in which case it is omitted from a
+/// serialized request
+///
+/// The dealer verifies signatures over the request it re-serializes, so
+/// fields added to the request must not appear in the bodies of clients that
+/// predate them
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
 // -------------
 // | Api Types |
 // -------------
@@ -42,9 +71,27 @@ where
 pub const PARTY_ID_HEADER: &str = "X-Party-Id";
 /// The header name for the signature
 pub const SIGNATURE_HEADER: &str = "X-Signature";
+/// The header name for the timestamp of a signed result fetch
+///
+/// Given in milliseconds since the unix epoch
+pub const TIMESTAMP_HEADER: &str = "X-Timestamp";
+/// The domain separator prepended to the payload of a signed result fetch
+const RESULT_FETCH_DOMAIN: &[u8] = b"renegade-dealer-result-fetch";
+/// The domain separator prepended to the payload of a signed session close
+const SESSION_CLOSE_DOMAIN: &[u8] = b"renegade-dealer-session-close";
+
+/// The maximum bit-length of a bounded random value, i.e. the random value in
+/// a truncation pair or an edaBit
+///
+/// Bounded below the bit-length of the scalar field's modulus so that the
+/// value never wraps
+pub const MAX_BOUNDED_BIT_LENGTH: u32 = 253;
 
 /// A type alias for the request
 pub type RequestId = uuid::Uuid;
+/// A type alias for a session, identified by the ID of the request that
+/// established it
+pub type SessionId = RequestId;
 
 /// The curve used for testing
 type Curve = ark_bn254::G1Projective;
@@ -52,6 +99,12 @@ type Curve = ark_bn254::G1Projective;
 type Scalar = ark_mpc::algebra::Scalar<Curve>;
 /// A type alias for a scalar share
 type ScalarShare = ark_mpc::algebra::ScalarShare<Curve>;
+/// A type alias for a point on the curve
+type CurvePoint = ark_mpc::algebra::CurvePoint<Curve>;
+
+/// The named request profiles configured on the dealer, mapping each profile
+/// name to the counts it requests
+pub type Profiles = BTreeMap<String, RequestCounts>;
 
 /// A response to a bad request
 #[derive(Debug, Serialize, Deserialize)]
@@ -62,16 +115,67 @@ pub struct ErrorResponse {
     pub message: &'static str,
 }
 
-/// A request for offline phase randomness from the dealer
-#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
-pub struct DealerRequest {
-    /// The public key of the first party in the exchange
-    #[serde(serialize_with = "serialize_key", deserialize_with = "deserialize_key")]
-    pub first_party_key: PublicKey,
-    /// The public key of the second party in the exchange
-    #[serde(serialize_with = "serialize_key", deserialize_with = "deserialize_key")]
-    pub second_party_key: PublicKey,
+/// The response to a request submitted asynchronously
+///
+/// The request ID identifies the request in subsequent status and result
+/// queries
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AsyncSubmitResponse {
+    /// The ID of the submitted request
+    pub request_id: RequestId,
+}
+
+/// The status of a request submitted asynchronously
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RequestStatus {
+    /// The request is awaiting its counterparty
+    Waiting,
+    /// Both parties have arrived and the dealer is generating values
+    Generating,
+    /// The party's shares are ready to be fetched
+    Ready,
+    /// The request expired or its result was already fetched
+    Expired,
+    /// The dealer rejected the request
+    Failed {
+        /// The reason the request failed
+        message: String,
+    },
+}
 
+/// The response listing the dealer's request profiles
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ListProfilesResponse {
+    /// The available profiles
+    pub profiles: Profiles,
+}
+
+/// Build the payload a party signs to fetch the result of an asynchronous
+/// request
+///
+/// The timestamp is given in milliseconds since the unix epoch and must be
+/// recent for the dealer to accept the signature
+pub fn result_fetch_payload(request_id: RequestId, party_id: PartyId, timestamp: u64) -> Vec<u8> {
+    timestamped_payload(RESULT_FETCH_DOMAIN, request_id, party_id, timestamp)
+}
+
+/// Build the payload a party signs to close a session
+///
+/// The timestamp is given in milliseconds since the unix epoch and must be
+/// recent for the dealer to accept the signature
+pub fn session_close_payload(session_id: SessionId, party_id: PartyId, timestamp: u64) -> Vec<u8> {
+    timestamped_payload(SESSION_CLOSE_DOMAIN, session_id, party_id, timestamp)
+}
+
+/// Build a domain separated payload over an ID, a party, and a timestamp
+fn timestamped_payload(domain: &[u8], id: RequestId, party_id: PartyId, timestamp: u64) -> Vec<u8> {
+    [domain, id.to_bytes_le().as_ref(), &party_id.to_le_bytes(), &timestamp.to_le_bytes()].concat()
+}
+
+/// The number of values of each type in a request
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct RequestCounts {
     /// The number of random bits to generate
     #[serde(default)]
     pub n_random_bits: u32,
@@ -84,12 +188,364 @@ pub struct DealerRequest {
     /// value, allowing them to mask an input with the share
     #[serde(default)]
     pub n_input_masks: u32,
+    /// The number of additional input masks to generate for the first party
+    /// only
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_first_party_input_masks: u32,
+    /// The number of additional input masks to generate for the second party
+    /// only
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_second_party_input_masks: u32,
     /// The number of inverse pairs to generate
     #[serde(default)]
     pub n_inverse_pairs: u32,
     /// The number of Beaver triples to generate
     #[serde(default)]
     pub n_triples: u32,
+    /// The number of square pairs to generate
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_square_pairs: u32,
+    /// The number of truncation pairs to generate
+    ///
+    /// A truncation pair holds shares of a random value r of at most
+    /// `truncation_bit_length` bits, and of r shifted right by
+    /// `truncation_shift` bits
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_truncation_pairs: u32,
+    /// The bit-length of the random value in each truncation pair
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub truncation_bit_length: u32,
+    /// The number of bits by which the random value in each truncation pair
+    /// is shifted
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub truncation_shift: u32,
+    /// The number of edaBits to generate
+    ///
+    /// An edaBit holds shares of a random value r < 2^k for k equal to
+    /// `edabit_bit_length`, along with shares of each of its k bits
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_edabits: u32,
+    /// The number of bits in each edaBit
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub edabit_bit_length: u32,
+    /// The number of powers tuples to generate
+    ///
+    /// A powers tuple holds shares of a random value r and of its powers up to
+    /// r^d for d equal to `power_degree`
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_power_tuples: u32,
+    /// The highest power in each powers tuple
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub power_degree: u32,
+    /// The number of prefix-product tuples to generate
+    ///
+    /// A prefix-product tuple holds shares of `prefix_product_length` random
+    /// values, their inverses, and the products of adjacent values
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_prefix_product_tuples: u32,
+    /// The number of random values in each prefix-product tuple
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub prefix_product_length: u32,
+    /// The number of inner-product triples to generate
+    ///
+    /// An inner-product triple holds shares of random vectors a and b of
+    /// length `inner_product_length`, and of their inner product c
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_inner_product_triples: u32,
+    /// The length of the vectors in each inner-product triple
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub inner_product_length: u32,
+    /// The number of matrix triples to generate
+    ///
+    /// A matrix triple holds shares of a random `matrix_rows` by
+    /// `matrix_inner` matrix A, a random `matrix_inner` by `matrix_cols`
+    /// matrix B, and their product C = AB
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_matrix_triples: u32,
+    /// The number of rows of A and C in each matrix triple
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub matrix_rows: u32,
+    /// The number of columns of A and rows of B in each matrix triple
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub matrix_inner: u32,
+    /// The number of columns of B and C in each matrix triple
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub matrix_cols: u32,
+    /// The number of authenticated sharings of zero to generate
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_zero_shares: u32,
+    /// The number of random points to generate
+    ///
+    /// A random point holds shares of a random scalar r along with the public
+    /// point r * G for the curve's generator G
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_point_shares: u32,
+    /// The number of AND triples over GF(2) to generate
+    ///
+    /// Authenticated under the binary MAC key rather than the scalar MAC key
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_binary_triples: u32,
+    /// The number of random values over Z/2^k to generate
+    ///
+    /// Values over Z/2^k are SPDZ2k-style shares, with k equal to
+    /// `ring_bit_length` and authenticated under a MAC key of
+    /// `ring_security_parameter` bits
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_ring_random_values: u32,
+    /// The number of random bits over Z/2^k to generate
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_ring_random_bits: u32,
+    /// The number of multiplication triples over Z/2^k to generate
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_ring_triples: u32,
+    /// The bit-length k of values over Z/2^k
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub ring_bit_length: u32,
+    /// The bit-length s of the MAC key for values over Z/2^k
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub ring_security_parameter: u32,
+    /// The number of random OTs to generate
+    ///
+    /// The party given by `ot_sender` receives random messages (m0, m1), the
+    /// other party receives a random choice bit b and the message m_b
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_random_ots: u32,
+    /// The number of correlated OTs to generate
+    ///
+    /// As random OTs, except that m1 = m0 XOR delta for a global delta known
+    /// to the sender
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub n_correlated_ots: u32,
+    /// The party acting as the sender in the OTs
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub ot_sender: PartyId,
+    /// The mode in which random bits, random values, and Beaver triples are
+    /// authenticated
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub mac_mode: MacMode,
+    /// Whether to compress the first party's random bits, random values, and
+    /// Beaver triples into a seed
+    ///
+    /// Only these sections are compressed: the first party's other values,
+    /// and its values dealt in the pairwise MAC mode, are sent in full. The
+    /// second party receives all of its shares in full
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub compressed: bool,
+}
+
+impl RequestCounts {
+    /// Return the total number of requested values
+    ///
+    /// Saturates at `u32::MAX`, so that an overflowing request exceeds any
+    /// size limit
+    pub fn total_values(&self) -> u32 {
+        [
+            self.n_random_bits,
+            self.n_random_values,
+            self.n_input_masks,
+            self.n_first_party_input_masks,
+            self.n_second_party_input_masks,
+            self.n_inverse_pairs,
+            self.n_triples,
+            self.n_square_pairs,
+            self.n_truncation_pairs,
+            self.n_edabit_values(),
+            self.n_power_tuple_values(),
+            self.n_prefix_product_values(),
+            self.n_inner_product_values(),
+            self.n_matrix_values(),
+            self.n_zero_shares,
+            self.n_point_shares,
+            self.n_binary_triples,
+            self.n_ring_values(),
+            self.n_random_ots,
+            self.n_correlated_ots,
+        ]
+        .into_iter()
+        .fold(0, u32::saturating_add)
+    }
+
+    /// The number of values counted for the requested edaBits
+    ///
+    /// Each edaBit counts as one value per bit, as generation is dominated by
+    /// its bits
+    pub fn n_edabit_values(&self) -> u32 {
+        self.n_edabits.saturating_mul(self.edabit_bit_length)
+    }
+
+    /// The number of values counted for the requested powers tuples
+    ///
+    /// Each tuple counts as one value per power
+    pub fn n_power_tuple_values(&self) -> u32 {
+        self.n_power_tuples.saturating_mul(self.power_degree)
+    }
+
+    /// The number of values counted for the requested prefix-product tuples
+    ///
+    /// Each tuple counts as one value per random value
+    pub fn n_prefix_product_values(&self) -> u32 {
+        self.n_prefix_product_tuples.saturating_mul(self.prefix_product_length)
+    }
+
+    /// The number of values counted for the requested inner-product triples
+    ///
+    /// Each triple counts as one value per element of its vectors
+    pub fn n_inner_product_values(&self) -> u32 {
+        self.n_inner_product_triples.saturating_mul(self.inner_product_length)
+    }
+
+    /// The number of values counted for the requested matrix triples
+    ///
+    /// Each triple counts as one value per entry of its three matrices
+    pub fn n_matrix_values(&self) -> u32 {
+        let a_entries = self.matrix_rows.saturating_mul(self.matrix_inner);
+        let b_entries = self.matrix_inner.saturating_mul(self.matrix_cols);
+        let c_entries = self.matrix_rows.saturating_mul(self.matrix_cols);
+        let entries = a_entries.saturating_add(b_entries).saturating_add(c_entries);
+
+        self.n_matrix_triples.saturating_mul(entries)
+    }
+
+    /// The number of values requested over Z/2^k
+    pub fn n_ring_values(&self) -> u32 {
+        self.n_ring_random_values
+            .saturating_add(self.n_ring_random_bits)
+            .saturating_add(self.n_ring_triples)
+    }
+
+    /// Check that the parameters of the requested values are well formed
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let truncation_valid = self.truncation_shift > 0
+            && self.truncation_shift <= self.truncation_bit_length
+            && self.truncation_bit_length <= MAX_BOUNDED_BIT_LENGTH;
+        if self.n_truncation_pairs > 0 && !truncation_valid {
+            return Err("Invalid truncation parameters");
+        }
+
+        let edabit_valid =
+            self.edabit_bit_length > 0 && self.edabit_bit_length <= MAX_BOUNDED_BIT_LENGTH;
+        if self.n_edabits > 0 && !edabit_valid {
+            return Err("Invalid edaBit bit-length");
+        }
+
+        if self.n_power_tuples > 0 && self.power_degree == 0 {
+            return Err("Invalid power degree");
+        }
+
+        if self.n_prefix_product_tuples > 0 && self.prefix_product_length == 0 {
+            return Err("Invalid prefix-product length");
+        }
+
+        if self.n_inner_product_triples > 0 && self.inner_product_length == 0 {
+            return Err("Invalid inner-product length");
+        }
+
+        let matrix_valid = self.matrix_rows > 0 && self.matrix_inner > 0 && self.matrix_cols > 0;
+        if self.n_matrix_triples > 0 && !matrix_valid {
+            return Err("Invalid matrix dimensions");
+        }
+
+        let ring_valid = self.ring_bit_length > 0
+            && self.ring_security_parameter > 0
+            && self.ring_bit_length.saturating_add(self.ring_security_parameter)
+                <= MAX_RING_BIT_LENGTH;
+        if self.n_ring_values() > 0 && !ring_valid {
+            return Err("Invalid ring parameters");
+        }
+
+        let n_ots = self.n_random_ots.saturating_add(self.n_correlated_ots);
+        if n_ots > 0 && self.ot_sender != PARTY0 && self.ot_sender != PARTY1 {
+            return Err("Invalid OT sender");
+        }
+
+        Ok(())
+    }
+
+    /// The total number of input masks for the first and second party
+    /// respectively
+    pub fn party_input_masks(&self) -> (u32, u32) {
+        (
+            self.n_input_masks.saturating_add(self.n_first_party_input_masks),
+            self.n_input_masks.saturating_add(self.n_second_party_input_masks),
+        )
+    }
+
+    /// Scale every count by the given multiplier, returning `None` on overflow
+    ///
+    /// The parameters of the requested values are left unchanged
+    pub fn checked_mul(&self, multiplier: u32) -> Option<Self> {
+        Some(Self {
+            n_random_bits: self.n_random_bits.checked_mul(multiplier)?,
+            n_random_values: self.n_random_values.checked_mul(multiplier)?,
+            n_input_masks: self.n_input_masks.checked_mul(multiplier)?,
+            n_first_party_input_masks: self.n_first_party_input_masks.checked_mul(multiplier)?,
+            n_second_party_input_masks: self.n_second_party_input_masks.checked_mul(multiplier)?,
+            n_inverse_pairs: self.n_inverse_pairs.checked_mul(multiplier)?,
+            n_triples: self.n_triples.checked_mul(multiplier)?,
+            n_square_pairs: self.n_square_pairs.checked_mul(multiplier)?,
+            n_truncation_pairs: self.n_truncation_pairs.checked_mul(multiplier)?,
+            n_edabits: self.n_edabits.checked_mul(multiplier)?,
+            n_power_tuples: self.n_power_tuples.checked_mul(multiplier)?,
+            n_prefix_product_tuples: self.n_prefix_product_tuples.checked_mul(multiplier)?,
+            n_inner_product_triples: self.n_inner_product_triples.checked_mul(multiplier)?,
+            n_matrix_triples: self.n_matrix_triples.checked_mul(multiplier)?,
+            n_zero_shares: self.n_zero_shares.checked_mul(multiplier)?,
+            n_point_shares: self.n_point_shares.checked_mul(multiplier)?,
+            n_binary_triples: self.n_binary_triples.checked_mul(multiplier)?,
+            n_ring_random_values: self.n_ring_random_values.checked_mul(multiplier)?,
+            n_ring_random_bits: self.n_ring_random_bits.checked_mul(multiplier)?,
+            n_ring_triples: self.n_ring_triples.checked_mul(multiplier)?,
+            n_random_ots: self.n_random_ots.checked_mul(multiplier)?,
+            n_correlated_ots: self.n_correlated_ots.checked_mul(multiplier)?,
+            ..*self
+        })
+    }
+}
+
+/// A reference to a named request profile configured on the dealer
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProfileRef {
+    /// The name of the profile
+    pub name: String,
+    /// The number of times the profile's counts are requested
+    #[serde(default = "default_multiplier")]
+    pub multiplier: u32,
+}
+
+/// The multiplier of a profile reference that does not specify one
+fn default_multiplier() -> u32 {
+    1
+}
+
+/// A request for offline phase randomness from the dealer
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct DealerRequest {
+    /// The public key of the first party in the exchange
+    #[serde(serialize_with = "serialize_key", deserialize_with = "deserialize_key")]
+    pub first_party_key: PublicKey,
+    /// The public key of the second party in the exchange
+    #[serde(serialize_with = "serialize_key", deserialize_with = "deserialize_key")]
+    pub second_party_key: PublicKey,
+
+    /// The number of values of each type to generate
+    ///
+    /// Left empty when the request references a profile, the dealer fills in
+    /// the profile's counts
+    #[serde(flatten)]
+    pub counts: RequestCounts,
+    /// The named profile from which to take the counts
+    ///
+    /// Both parties must reference the same profile and multiplier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<ProfileRef>,
+
+    /// The session in which to generate values
+    ///
+    /// A request whose session ID equals its request ID establishes a new
+    /// session. Later requests from the same parties that reference the
+    /// session are authenticated under the same MAC key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<SessionId>,
 }
 
 impl DealerRequest {
@@ -98,56 +554,288 @@ impl DealerRequest {
         Self {
             first_party_key,
             second_party_key,
-            n_random_bits: 0,
-            n_random_values: 0,
-            n_input_masks: 0,
-            n_inverse_pairs: 0,
-            n_triples: 0,
+            counts: RequestCounts::default(),
+            profile: None,
+            session_id: None,
         }
     }
 
     /// Return the total number of requested values
     pub fn total_values(&self) -> u32 {
-        self.n_random_bits
-            + self.n_random_values
-            + self.n_input_masks
-            + self.n_inverse_pairs
-            + self.n_triples
+        self.counts.total_values()
     }
 
     /// Set the number of random bits to generate
     pub fn with_n_random_bits(mut self, n_random_bits: u32) -> Self {
-        self.n_random_bits = n_random_bits;
+        self.counts.n_random_bits = n_random_bits;
         self
     }
 
     /// Set the number of shared random values to generate
     pub fn with_n_random_values(mut self, n_random_values: u32) -> Self {
-        self.n_random_values = n_random_values;
+        self.counts.n_random_values = n_random_values;
         self
     }
 
     /// Set the number of input masks to generate
     pub fn with_n_input_masks(mut self, n_input_masks: u32) -> Self {
-        self.n_input_masks = n_input_masks;
+        self.counts.n_input_masks = n_input_masks;
+        self
+    }
+
+    /// Set the number of additional input masks to generate for the first
+    /// party only
+    pub fn with_n_first_party_input_masks(mut self, n_input_masks: u32) -> Self {
+        self.counts.n_first_party_input_masks = n_input_masks;
+        self
+    }
+
+    /// Set the number of additional input masks to generate for the second
+    /// party only
+    pub fn with_n_second_party_input_masks(mut self, n_input_masks: u32) -> Self {
+        self.counts.n_second_party_input_masks = n_input_masks;
         self
     }
 
     /// Set the number of inverse pairs to generate
     pub fn with_n_inverse_pairs(mut self, n_inverse_pairs: u32) -> Self {
-        self.n_inverse_pairs = n_inverse_pairs;
+        self.counts.n_inverse_pairs = n_inverse_pairs;
         self
     }
 
     /// Set the number of Beaver triples to generate
     pub fn with_n_triples(mut self, n_triples: u32) -> Self {
-        self.n_triples = n_triples;
+        self.counts.n_triples = n_triples;
+        self
+    }
+
+    /// Set the number of square pairs to generate
+    pub fn with_n_square_pairs(mut self, n_square_pairs: u32) -> Self {
+        self.counts.n_square_pairs = n_square_pairs;
+        self
+    }
+
+    /// Set the number of truncation pairs to generate, along with the
+    /// bit-length of their random values and the shift applied to them
+    pub fn with_n_truncation_pairs(
+        mut self,
+        n_truncation_pairs: u32,
+        bit_length: u32,
+        shift: u32,
+    ) -> Self {
+        self.counts.n_truncation_pairs = n_truncation_pairs;
+        self.counts.truncation_bit_length = bit_length;
+        self.counts.truncation_shift = shift;
+        self
+    }
+
+    /// Set the number of edaBits to generate, along with their bit-length
+    pub fn with_n_edabits(mut self, n_edabits: u32, bit_length: u32) -> Self {
+        self.counts.n_edabits = n_edabits;
+        self.counts.edabit_bit_length = bit_length;
+        self
+    }
+
+    /// Set the number of powers tuples to generate, along with the highest
+    /// power in each
+    pub fn with_n_power_tuples(mut self, n_power_tuples: u32, degree: u32) -> Self {
+        self.counts.n_power_tuples = n_power_tuples;
+        self.counts.power_degree = degree;
+        self
+    }
+
+    /// Set the number of prefix-product tuples to generate, along with the
+    /// number of random values in each
+    pub fn with_n_prefix_product_tuples(mut self, n_tuples: u32, length: u32) -> Self {
+        self.counts.n_prefix_product_tuples = n_tuples;
+        self.counts.prefix_product_length = length;
+        self
+    }
+
+    /// Set the number of inner-product triples to generate, along with the
+    /// length of their vectors
+    pub fn with_n_inner_product_triples(mut self, n_triples: u32, length: u32) -> Self {
+        self.counts.n_inner_product_triples = n_triples;
+        self.counts.inner_product_length = length;
+        self
+    }
+
+    /// Set the number of matrix triples to generate, along with the
+    /// dimensions of their matrices
+    ///
+    /// A is `rows` by `inner`, B is `inner` by `cols`, and C is `rows` by
+    /// `cols`
+    pub fn with_n_matrix_triples(
+        mut self,
+        n_triples: u32,
+        rows: u32,
+        inner: u32,
+        cols: u32,
+    ) -> Self {
+        self.counts.n_matrix_triples = n_triples;
+        self.counts.matrix_rows = rows;
+        self.counts.matrix_inner = inner;
+        self.counts.matrix_cols = cols;
+        self
+    }
+
+    /// Set the number of authenticated sharings of zero to generate
+    pub fn with_n_zero_shares(mut self, n_zero_shares: u32) -> Self {
+        self.counts.n_zero_shares = n_zero_shares;
+        self
+    }
+
+    /// Set the number of random points to generate
+    pub fn with_n_point_shares(mut self, n_point_shares: u32) -> Self {
+        self.counts.n_point_shares = n_point_shares;
+        self
+    }
+
+    /// Set the number of AND triples over GF(2) to generate
+    pub fn with_n_binary_triples(mut self, n_binary_triples: u32) -> Self {
+        self.counts.n_binary_triples = n_binary_triples;
+        self
+    }
+
+    /// Set the parameters of values over Z/2^k
+    ///
+    /// Values are `bit_length` bits, authenticated under a MAC key of
+    /// `security_parameter` bits
+    pub fn with_ring_params(mut self, bit_length: u32, security_parameter: u32) -> Self {
+        self.counts.ring_bit_length = bit_length;
+        self.counts.ring_security_parameter = security_parameter;
+        self
+    }
+
+    /// Set the number of random values over Z/2^k to generate
+    pub fn with_n_ring_random_values(mut self, n_values: u32) -> Self {
+        self.counts.n_ring_random_values = n_values;
+        self
+    }
+
+    /// Set the number of random bits over Z/2^k to generate
+    pub fn with_n_ring_random_bits(mut self, n_bits: u32) -> Self {
+        self.counts.n_ring_random_bits = n_bits;
+        self
+    }
+
+    /// Set the number of multiplication triples over Z/2^k to generate
+    pub fn with_n_ring_triples(mut self, n_triples: u32) -> Self {
+        self.counts.n_ring_triples = n_triples;
+        self
+    }
+
+    /// Set the number of random OTs to generate
+    pub fn with_n_random_ots(mut self, n_ots: u32) -> Self {
+        self.counts.n_random_ots = n_ots;
+        self
+    }
+
+    /// Set the number of correlated OTs to generate
+    pub fn with_n_correlated_ots(mut self, n_ots: u32) -> Self {
+        self.counts.n_correlated_ots = n_ots;
+        self
+    }
+
+    /// Set the party acting as the sender in the OTs
+    pub fn with_ot_sender(mut self, party_id: PartyId) -> Self {
+        self.counts.ot_sender = party_id;
+        self
+    }
+
+    /// Set the mode in which random bits, random values, and Beaver triples
+    /// are authenticated
+    pub fn with_mac_mode(mut self, mac_mode: MacMode) -> Self {
+        self.counts.mac_mode = mac_mode;
+        self
+    }
+
+    /// Compress the first party's random bits, random values, and Beaver
+    /// triples into a seed
+    pub fn with_compression(mut self) -> Self {
+        self.counts.compressed = true;
+        self
+    }
+
+    /// Request the counts of a named profile
+    pub fn with_profile(self, name: &str) -> Self {
+        self.with_scaled_profile(name, 1 /* multiplier */)
+    }
+
+    /// Request the counts of a named profile, scaled by the given multiplier
+    pub fn with_scaled_profile(mut self, name: &str, multiplier: u32) -> Self {
+        self.profile = Some(ProfileRef { name: name.to_string(), multiplier });
         self
     }
+
+    /// Set the session in which to generate values
+    pub fn with_session(mut self, session_id: SessionId) -> Self {
+        self.session_id = Some(session_id);
+        self
+    }
+}
+
+/// The shares of a prefix-product tuple
+///
+/// Holds random values r_1, ..., r_l, their inverses, and the adjacent products
+/// r_{i+1} * r_i^-1, from which a party computes all prefix products of l
+/// secret values in a constant number of rounds
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct PrefixProductTuple {
+    /// The random values
+    pub r: Vec<ScalarShare>,
+    /// The inverses of the random values
+    pub r_inv: Vec<ScalarShare>,
+    /// The products of each random value with the inverse of its predecessor
+    ///
+    /// Holds one fewer entry than the random values
+    pub adjacent: Vec<ScalarShare>,
+}
+
+/// The shares of an inner-product triple
+///
+/// Holds random vectors a and b, and their inner product c = <a, b>
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InnerProductTriple {
+    /// The first vector
+    pub a: Vec<ScalarShare>,
+    /// The second vector
+    pub b: Vec<ScalarShare>,
+    /// The inner product of the vectors
+    pub c: ScalarShare,
+}
+
+/// The shares of a matrix triple
+///
+/// Holds random matrices A and B, and their product C = AB, each in row-major
+/// order with the dimensions given in the request
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct MatrixTriple {
+    /// The left matrix
+    pub a: Vec<ScalarShare>,
+    /// The right matrix
+    pub b: Vec<ScalarShare>,
+    /// The product of the matrices
+    pub c: Vec<ScalarShare>,
+}
+
+/// The shares of a random point
+///
+/// Holds shares of a random scalar r, and the public point r * G for the
+/// curve's generator G
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct PointShare {
+    /// The shares of the scalar
+    pub r: ScalarShare,
+    /// The point r * G, known to both parties
+    pub commitment: CurvePoint,
 }
 
 /// A response from the Dealer
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+///
+/// The `Debug` implementation redacts all shares and MACs so that responses
+/// may be safely logged
+#[derive(Clone, Serialize, Deserialize, Default)]
 pub struct DealerResponse {
     /// The share of the mac key
     pub mac_key_share: Scalar,
@@ -159,7 +847,8 @@ pub struct DealerResponse {
     ///
     /// Holds the plaintext values of the input masks, the shares of these
     /// cleartext values, and the shares of the counterparty's input masks in
-    /// order
+    /// order. The party's own masks and the counterparty's masks may differ
+    /// in number
     pub input_masks: (Vec<Scalar>, Vec<ScalarShare>, Vec<ScalarShare>),
     /// The inverse pairs
     ///
@@ -167,6 +856,69 @@ pub struct DealerResponse {
     pub inverse_pairs: (Vec<ScalarShare>, Vec<ScalarShare>),
     /// The triples
     pub beaver_triples: (Vec<ScalarShare>, Vec<ScalarShare>, Vec<ScalarShare>),
+    /// The square pairs
+    ///
+    /// Random values r, r^2 in the scalar field
+    #[serde(default)]
+    pub square_pairs: (Vec<ScalarShare>, Vec<ScalarShare>),
+    /// The truncation pairs
+    ///
+    /// Random values r of bounded bit-length and r >> k, with the bit-length
+    /// and shift k given in the request
+    #[serde(default)]
+    pub truncation_pairs: (Vec<ScalarShare>, Vec<ScalarShare>),
+    /// The edaBits
+    ///
+    /// Random values r < 2^k and, for each value, its k bits in little-endian
+    /// order, with k given in the request
+    #[serde(default)]
+    pub edabits: (Vec<ScalarShare>, Vec<Vec<ScalarShare>>),
+    /// The powers tuples
+    ///
+    /// For each random value r, the powers r, r^2, ..., r^d with d given in
+    /// the request
+    #[serde(default)]
+    pub power_tuples: Vec<Vec<ScalarShare>>,
+    /// The prefix-product tuples
+    #[serde(default)]
+    pub prefix_product_tuples: Vec<PrefixProductTuple>,
+    /// The inner-product triples
+    #[serde(default)]
+    pub inner_product_triples: Vec<InnerProductTriple>,
+    /// The matrix triples
+    #[serde(default)]
+    pub matrix_triples: Vec<MatrixTriple>,
+    /// The authenticated sharings of zero
+    #[serde(default)]
+    pub zero_shares: Vec<ScalarShare>,
+    /// The random points
+    #[serde(default)]
+    pub point_shares: Vec<PointShare>,
+    /// The share of the binary MAC key, which authenticates values over GF(2)
+    #[serde(
+        default,
+        serialize_with = "serialize_mac_key",
+        deserialize_with = "deserialize_mac_key"
+    )]
+    pub binary_mac_key_share: BinaryMacKey,
+    /// The AND triples over GF(2)
+    #[serde(default)]
+    pub binary_triples: BinaryTriples,
+    /// The SPDZ2k-style values over Z/2^k
+    #[serde(default)]
+    pub ring: RingResponse,
+    /// The OT correlations
+    #[serde(default)]
+    pub ot: OtResponse,
+    /// The values authenticated with pairwise MACs, when requested
+    #[serde(default)]
+    pub pairwise: PairwiseResponse,
+    /// The seed from which the party's random bits, random values, and Beaver
+    /// triples are expanded, when compressed
+    ///
+    /// See `expand_compressed`
+    #[serde(default)]
+    pub compressed: Option<CompressedShares>,
 }
 
 impl DealerResponse {
@@ -203,14 +955,327 @@ impl DealerResponse {
 
         self.beaver_triples = (a, b, c);
     }
+
+    /// Set the square pairs
+    pub fn set_square_pairs(&mut self, r: Vec<ScalarShare>, r_squared: Vec<ScalarShare>) {
+        assert_eq!(r.len(), r_squared.len());
+        self.square_pairs = (r, r_squared);
+    }
+
+    /// Set the truncation pairs
+    pub fn set_truncation_pairs(&mut self, r: Vec<ScalarShare>, r_shifted: Vec<ScalarShare>) {
+        assert_eq!(r.len(), r_shifted.len());
+        self.truncation_pairs = (r, r_shifted);
+    }
+
+    /// Set the edaBits
+    pub fn set_edabits(&mut self, r: Vec<ScalarShare>, bits: Vec<Vec<ScalarShare>>) {
+        assert_eq!(r.len(), bits.len());
+        self.edabits = (r, bits);
+    }
+
+    /// Set the powers tuples
+    pub fn set_power_tuples(&mut self, tuples: Vec<Vec<ScalarShare>>) {
+        self.power_tuples = tuples;
+    }
+
+    /// Set the prefix-product tuples
+    pub fn set_prefix_product_tuples(&mut self, tuples: Vec<PrefixProductTuple>) {
+        self.prefix_product_tuples = tuples;
+    }
+
+    /// Set the inner-product triples
+    pub fn set_inner_product_triples(&mut self, triples: Vec<InnerProductTriple>) {
+        self.inner_product_triples = triples;
+    }
+
+    /// Set the matrix triples
+    pub fn set_matrix_triples(&mut self, triples: Vec<MatrixTriple>) {
+        self.matrix_triples = triples;
+    }
+
+    /// Set the sharings of zero
+    pub fn set_zero_shares(&mut self, shares: Vec<ScalarShare>) {
+        self.zero_shares = shares;
+    }
+
+    /// Set the random points
+    pub fn set_point_shares(&mut self, points: Vec<PointShare>) {
+        self.point_shares = points;
+    }
+
+    /// Set the AND triples over GF(2)
+    pub fn set_binary_triples(&mut self, triples: BinaryTriples) {
+        let n = triples.len();
+        assert_eq!(n, triples.b.len());
+        assert_eq!(n, triples.c.len());
+
+        self.binary_triples = triples;
+    }
+
+    /// Set the values over Z/2^k
+    pub fn set_ring(&mut self, ring: RingResponse) {
+        self.ring = ring;
+    }
+
+    /// Set the OT correlations
+    pub fn set_ot(&mut self, ot: OtResponse) {
+        self.ot = ot;
+    }
+
+    /// Expand the party's compressed shares, if any, into their sections
+    ///
+    /// The expanded shares precede any shares already in the sections
+    pub fn expand_compressed(&mut self) {
+        let Some(compressed) = self.compressed.take() else {
+            return;
+        };
+
+        let expanded = compressed.expand();
+        let (a, b, c) = expanded.beaver_triples;
+        self.random_bits.splice(0..0, expanded.random_bits);
+        self.random_values.splice(0..0, expanded.random_values);
+        self.beaver_triples.0.splice(0..0, a);
+        self.beaver_triples.1.splice(0..0, b);
+        self.beaver_triples.2.splice(0..0, c);
+    }
+
+    /// Set the values authenticated with pairwise MACs
+    pub fn set_pairwise(&mut self, pairwise: PairwiseResponse) {
+        self.pairwise = pairwise;
+    }
+
+    /// Split the response into chunks, each holding at most `chunk_size`
+    /// entries of every section
+    ///
+    /// Each chunk carries the MAC key shares, the original response is
+    /// recovered by extending the first chunk with the rest in order
+    pub fn into_chunks(self, chunk_size: usize) -> Vec<DealerResponse> {
+        assert!(chunk_size > 0, "chunk size must be positive");
+        let n_chunks = self.max_section_len().div_ceil(chunk_size).max(1);
+        let empty = DealerResponse {
+            mac_key_share: self.mac_key_share,
+            binary_mac_key_share: self.binary_mac_key_share,
+            ring: self.ring.empty(),
+            pairwise: PairwiseResponse { mac_key: self.pairwise.mac_key, ..Default::default() },
+            ..Default::default()
+        };
+        let mut chunks = vec![empty; n_chunks];
+        chunks[0].compressed = self.compressed;
+
+        let (masks, mask_shares, counterparty_mask_shares) = self.input_masks;
+        let (r, r_inv) = self.inverse_pairs;
+        let (a, b, c) = self.beaver_triples;
+        let (sq_r, sq_r_squared) = self.square_pairs;
+        let (trunc_r, trunc_r_shifted) = self.truncation_pairs;
+        let (edabit_r, edabit_bits) = self.edabits;
+
+        distribute(self.random_bits, chunk_size, &mut chunks, |c| &mut c.random_bits);
+        distribute(self.random_values, chunk_size, &mut chunks, |c| &mut c.random_values);
+        distribute(masks, chunk_size, &mut chunks, |c| &mut c.input_masks.0);
+        distribute(mask_shares, chunk_size, &mut chunks, |c| &mut c.input_masks.1);
+        distribute(counterparty_mask_shares, chunk_size, &mut chunks, |c| &mut c.input_masks.2);
+        distribute(r, chunk_size, &mut chunks, |c| &mut c.inverse_pairs.0);
+        distribute(r_inv, chunk_size, &mut chunks, |c| &mut c.inverse_pairs.1);
+        distribute(a, chunk_size, &mut chunks, |c| &mut c.beaver_triples.0);
+        distribute(b, chunk_size, &mut chunks, |c| &mut c.beaver_triples.1);
+        distribute(c, chunk_size, &mut chunks, |c| &mut c.beaver_triples.2);
+        distribute(sq_r, chunk_size, &mut chunks, |c| &mut c.square_pairs.0);
+        distribute(sq_r_squared, chunk_size, &mut chunks, |c| &mut c.square_pairs.1);
+        distribute(trunc_r, chunk_size, &mut chunks, |c| &mut c.truncation_pairs.0);
+        distribute(trunc_r_shifted, chunk_size, &mut chunks, |c| &mut c.truncation_pairs.1);
+        distribute(edabit_r, chunk_size, &mut chunks, |c| &mut c.edabits.0);
+        distribute(edabit_bits, chunk_size, &mut chunks, |c| &mut c.edabits.1);
+        distribute(self.power_tuples, chunk_size, &mut chunks, |c| &mut c.power_tuples);
+        distribute(self.prefix_product_tuples, chunk_size, &mut chunks, |c| {
+            &mut c.prefix_product_tuples
+        });
+        distribute(self.inner_product_triples, chunk_size, &mut chunks, |c| {
+            &mut c.inner_product_triples
+        });
+        distribute(self.matrix_triples, chunk_size, &mut chunks, |c| &mut c.matrix_triples);
+        distribute(self.zero_shares, chunk_size, &mut chunks, |c| &mut c.zero_shares);
+        distribute(self.point_shares, chunk_size, &mut chunks, |c| &mut c.point_shares);
+
+        let ring = self.ring;
+        let (ring_a, ring_b, ring_c) = ring.triples;
+        distribute(ring.random_values, chunk_size, &mut chunks, |c| &mut c.ring.random_values);
+        distribute(ring.random_bits, chunk_size, &mut chunks, |c| &mut c.ring.random_bits);
+        distribute(ring_a, chunk_size, &mut chunks, |c| &mut c.ring.triples.0);
+        distribute(ring_b, chunk_size, &mut chunks, |c| &mut c.ring.triples.1);
+        distribute(ring_c, chunk_size, &mut chunks, |c| &mut c.ring.triples.2);
+
+        let pairwise = self.pairwise;
+        let (pairwise_a, pairwise_b, pairwise_c) = pairwise.beaver_triples;
+        distribute(pairwise.random_bits, chunk_size, &mut chunks, |c| &mut c.pairwise.random_bits);
+        distribute(pairwise.random_values, chunk_size, &mut chunks, |c| {
+            &mut c.pairwise.random_values
+        });
+        distribute(pairwise_a, chunk_size, &mut chunks, |c| &mut c.pairwise.beaver_triples.0);
+        distribute(pairwise_b, chunk_size, &mut chunks, |c| &mut c.pairwise.beaver_triples.1);
+        distribute(pairwise_c, chunk_size, &mut chunks, |c| &mut c.pairwise.beaver_triples.2);
+
+        // Binary triples and OT choice bits are packed, so are split by slicing
+        let n_binary = self.binary_triples.len();
+        for (i, chunk) in chunks.iter_mut().enumerate() {
+            let start = (i * chunk_size).min(n_binary);
+            let n = chunk_size.min(n_binary - start);
+            chunk.binary_triples = self.binary_triples.slice(start, n);
+            chunk.ot = self.ot.slice(i * chunk_size, chunk_size);
+        }
+
+        chunks
+    }
+
+    /// Append the sections of another response to this one
+    pub fn extend(&mut self, other: DealerResponse) {
+        self.compressed = self.compressed.or(other.compressed);
+        self.random_bits.extend(other.random_bits);
+        self.random_values.extend(other.random_values);
+        self.input_masks.0.extend(other.input_masks.0);
+        self.input_masks.1.extend(other.input_masks.1);
+        self.input_masks.2.extend(other.input_masks.2);
+        self.inverse_pairs.0.extend(other.inverse_pairs.0);
+        self.inverse_pairs.1.extend(other.inverse_pairs.1);
+        self.beaver_triples.0.extend(other.beaver_triples.0);
+        self.beaver_triples.1.extend(other.beaver_triples.1);
+        self.beaver_triples.2.extend(other.beaver_triples.2);
+        self.square_pairs.0.extend(other.square_pairs.0);
+        self.square_pairs.1.extend(other.square_pairs.1);
+        self.truncation_pairs.0.extend(other.truncation_pairs.0);
+        self.truncation_pairs.1.extend(other.truncation_pairs.1);
+        self.edabits.0.extend(other.edabits.0);
+        self.edabits.1.extend(other.edabits.1);
+        self.power_tuples.extend(other.power_tuples);
+        self.prefix_product_tuples.extend(other.prefix_product_tuples);
+        self.inner_product_triples.extend(other.inner_product_triples);
+        self.matrix_triples.extend(other.matrix_triples);
+        self.zero_shares.extend(other.zero_shares);
+        self.point_shares.extend(other.point_shares);
+        self.binary_triples.extend(&other.binary_triples);
+        self.ring.extend(other.ring);
+        self.ot.extend(&other.ot);
+        self.pairwise.extend(other.pairwise);
+    }
+
+    /// The length of the longest section in the response
+    fn max_section_len(&self) -> usize {
+        [
+            self.random_bits.len(),
+            self.random_values.len(),
+            self.input_masks.0.len(),
+            self.input_masks.1.len(),
+            self.input_masks.2.len(),
+            self.inverse_pairs.0.len(),
+            self.inverse_pairs.1.len(),
+            self.beaver_triples.0.len(),
+            self.square_pairs.0.len(),
+            self.truncation_pairs.0.len(),
+            self.edabits.0.len(),
+            self.power_tuples.len(),
+            self.prefix_product_tuples.len(),
+            self.inner_product_triples.len(),
+            self.matrix_triples.len(),
+            self.zero_shares.len(),
+            self.point_shares.len(),
+            self.binary_triples.len(),
+            self.ring.max_section_len(),
+            self.ot.n_random(),
+            self.ot.n_correlated(),
+            self.pairwise.max_section_len(),
+        ]
+        .into_iter()
+        .max()
+        .unwrap_or_default()
+    }
+}
+
+/// Distribute the values of a section across a set of chunks in order, at
+/// most `chunk_size` values per chunk
+fn distribute<T>(
+    values: Vec<T>,
+    chunk_size: usize,
+    chunks: &mut [DealerResponse],
+    section: fn(&mut DealerResponse) -> &mut Vec<T>,
+) {
+    let mut values = values.into_iter();
+    for chunk in chunks.iter_mut() {
+        section(chunk).extend(values.by_ref().take(chunk_size));
+    }
+}
+
+impl Debug for DealerResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let (masks, mask_shares, counterparty_mask_shares) = &self.input_masks;
+        let (r, r_inv) = &self.inverse_pairs;
+        let (a, b, c) = &self.beaver_triples;
+        let (sq_r, sq_r_squared) = &self.square_pairs;
+        let (trunc_r, trunc_r_shifted) = &self.truncation_pairs;
+        let (edabit_r, edabit_bits) = &self.edabits;
+        let n_edabit_bits = edabit_bits.iter().map(Vec::len).sum();
+        let n_powers = self.power_tuples.iter().map(Vec::len).sum();
+
+        f.debug_struct("DealerResponse")
+            .field("mac_key_share", &Redacted(1))
+            .field("random_bits", &Redacted(self.random_bits.len()))
+            .field("random_values", &Redacted(self.random_values.len()))
+            .field(
+                "input_masks",
+                &(
+                    Redacted(masks.len()),
+                    Redacted(mask_shares.len()),
+                    Redacted(counterparty_mask_shares.len()),
+                ),
+            )
+            .field("inverse_pairs", &(Redacted(r.len()), Redacted(r_inv.len())))
+            .field("beaver_triples", &(Redacted(a.len()), Redacted(b.len()), Redacted(c.len())))
+            .field("square_pairs", &(Redacted(sq_r.len()), Redacted(sq_r_squared.len())))
+            .field("truncation_pairs", &(Redacted(trunc_r.len()), Redacted(trunc_r_shifted.len())))
+            .field("edabits", &(Redacted(edabit_r.len()), Redacted(n_edabit_bits)))
+            .field("power_tuples", &Redacted(n_powers))
+            .field("prefix_product_tuples", &Redacted(self.prefix_product_tuples.len()))
+            .field("inner_product_triples", &Redacted(self.inner_product_triples.len()))
+            .field("matrix_triples", &Redacted(self.matrix_triples.len()))
+            .field("zero_shares", &Redacted(self.zero_shares.len()))
+            .field("point_shares", &Redacted(self.point_shares.len()))
+            .field("binary_mac_key_share", &Redacted(1))
+            .field("binary_triples", &Redacted(self.binary_triples.len()))
+            .field("ring_mac_key_share", &Redacted(1))
+            .field("ring_random_values", &Redacted(self.ring.random_values.len()))
+            .field("ring_random_bits", &Redacted(self.ring.random_bits.len()))
+            .field("ring_triples", &Redacted(self.ring.triples.0.len()))
+            .field("random_ots", &Redacted(self.ot.n_random()))
+            .field("correlated_ots", &Redacted(self.ot.n_correlated()))
+            .field("pairwise_mac_key", &Redacted(1))
+            .field("compressed", &Redacted(self.compressed.is_some() as usize))
+            .field("pairwise_random_bits", &Redacted(self.pairwise.random_bits.len()))
+            .field("pairwise_random_values", &Redacted(self.pairwise.random_values.len()))
+            .field("pairwise_triples", &Redacted(self.pairwise.beaver_triples.0.len()))
+            .finish()
+    }
+}
+
+/// A placeholder for secret values in debug output
+///
+/// Holds only the number of values redacted, so that shares and MACs never
+/// reach a log line
+struct Redacted(usize);
+impl Debug for Redacted {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "<{} redacted>", self.0)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use k256::SecretKey;
-    use rand::thread_rng;
+    use rand::{thread_rng, Rng};
 
-    use crate::DealerRequest;
+    use crate::{
+        binary::{BinaryShares, BinaryTriples, PackedBits},
+        ring::RingShare,
+        DealerRequest, DealerResponse, ProfileRef, Scalar, ScalarShare, MAX_BOUNDED_BIT_LENGTH,
+    };
 
     /// Test serialization + deserialization of the `DealerRequest`
     #[test]
@@ -228,4 +1293,107 @@ mod test {
 
         assert_eq!(req, de);
     }
+
+    /// Tests validation of the parameters of requested values
+    #[test]
+    fn test_validate_params() {
+        let mut rng = thread_rng();
+        let key = SecretKey::random(&mut rng).public_key();
+        let req = DealerRequest::new(key, key);
+
+        let valid = req
+            .clone()
+            .with_n_truncation_pairs(1, 64, 16)
+            .with_n_edabits(1, 64)
+            .with_n_ring_triples(1)
+            .with_ring_params(64, 64);
+        assert!(valid.counts.validate().is_ok());
+        // Parameters are only checked when values are requested
+        assert!(req.clone().with_n_edabits(0, 0).counts.validate().is_ok());
+
+        let invalid = [
+            req.clone().with_n_truncation_pairs(1, 16, 32),
+            req.clone().with_n_truncation_pairs(1, 64, 0),
+            req.clone().with_n_truncation_pairs(1, MAX_BOUNDED_BIT_LENGTH + 1, 16),
+            req.clone().with_n_edabits(1, 0),
+            req.clone().with_n_edabits(1, MAX_BOUNDED_BIT_LENGTH + 1),
+            req.clone().with_n_power_tuples(1, 0),
+            req.clone().with_n_prefix_product_tuples(1, 0),
+            req.clone().with_n_inner_product_triples(1, 0),
+            req.clone().with_n_matrix_triples(1, 2, 0, 2),
+            req.clone().with_n_ring_triples(1).with_ring_params(0, 64),
+            req.clone().with_n_ring_random_bits(1).with_ring_params(64, 65),
+            req.with_n_random_ots(1).with_ot_sender(2),
+        ];
+        assert!(invalid.iter().all(|req| req.counts.validate().is_err()));
+    }
+
+    /// Tests that a profile reference without a multiplier defaults to one
+    #[test]
+    fn test_profile_default_multiplier() {
+        let profile: ProfileRef = serde_json::from_str(r#"{"name": "settle"}"#).unwrap();
+        assert_eq!(profile, ProfileRef { name: "settle".to_string(), multiplier: 1 });
+    }
+
+    /// Tests that splitting a response into chunks and reassembling them
+    /// recovers the original response
+    #[test]
+    fn test_response_chunks() {
+        let mut rng = thread_rng();
+        let mut random_share =
+            || ScalarShare::new(Scalar::random(&mut rng), Scalar::random(&mut rng));
+
+        let mut resp = DealerResponse::default();
+        resp.set_random_bits((0..7).map(|_| random_share()).collect());
+        resp.set_random_values((0..3).map(|_| random_share()).collect());
+        let triples = (0..5).map(|_| (random_share(), random_share(), random_share()));
+        let (a, b, c) = triples.fold((vec![], vec![], vec![]), |(mut a, mut b, mut c), t| {
+            a.push(t.0);
+            b.push(t.1);
+            c.push(t.2);
+            (a, b, c)
+        });
+        resp.set_triples(a, b, c);
+
+        let mut random_binary = |n| BinaryShares {
+            bits: PackedBits::from_bits((0..n).map(|_| rng.gen_bool(0.5 /* p */))),
+            macs: (0..n).map(|_| rng.gen()).collect(),
+        };
+        let (a, b, c) = (random_binary(5), random_binary(5), random_binary(5));
+        resp.set_binary_triples(BinaryTriples { a, b, c });
+
+        let ring_values = (0..7).map(|_| RingShare { share: rng.gen(), mac: rng.gen() }).collect();
+        resp.ring.set_random_values(ring_values);
+
+        let chunks = resp.clone().into_chunks(2 /* chunk_size */);
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks.iter().all(|c| c.random_bits.len() <= 2));
+        assert!(chunks.iter().all(|c| c.binary_triples.len() <= 2));
+        assert!(chunks.iter().all(|c| c.ring.random_values.len() <= 2));
+
+        let mut chunks = chunks.into_iter();
+        let mut reassembled = chunks.next().unwrap();
+        chunks.for_each(|c| reassembled.extend(c));
+
+        let ser = |r: &DealerResponse| serde_json::to_string(r).unwrap();
+        assert_eq!(ser(&resp), ser(&reassembled));
+    }
+
+    /// Tests that the debug output of a `DealerResponse` does not contain any
+    /// shares
+    #[test]
+    fn test_response_debug_redacted() {
+        let mut rng = thread_rng();
+        let mac_key_share = Scalar::random(&mut rng);
+        let share = ScalarShare::new(Scalar::random(&mut rng), Scalar::random(&mut rng));
+
+        let mut resp = DealerResponse { mac_key_share, ..Default::default() };
+        resp.set_random_values(vec![share; 3]);
+        let debug = format!("{resp:?}");
+
+        assert!(debug.contains("random_values: <3 redacted>"));
+        for secret in [mac_key_share, share.share(), share.mac()] {
+            assert!(!debug.contains(&format!("{secret:?}")));
+        }
+    }
 }
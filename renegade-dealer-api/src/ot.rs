@@ -0,0 +1,168 @@
+//! Random oblivious-transfer correlations
+//!
+//! The dealer plays the role of an OT functionality: one party acts as the
+//! sender and receives random messages, the other acts as the receiver and
+//! receives random choice bits along with the chosen messages. Which party
+//! sends is fixed by the request
+//!
+//! Two flavours are dealt:
+//! - Random OT, wherein the sender holds independent messages (m0, m1) and the
+//!   receiver holds (b, m_b)
+//! - Correlated OT, wherein the sender holds a global delta and messages m0,
+//!   with m1 = m0 XOR delta, and the receiver holds (b, m_b)
+//!
+//! Messages are 128-bit blocks, serialized as base64 encoded little-endian
+//! bytes alongside packed choice bits
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::binary::{
+    deserialize_blocks, deserialize_mac_key, serialize_blocks, serialize_mac_key, PackedBits,
+};
+
+/// A message in an OT correlation
+pub type OtBlock = u128;
+
+/// The sender's side of the OT correlations in a response
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct OtSenderShares {
+    /// The global delta of the correlated OTs
+    #[serde(serialize_with = "serialize_mac_key", deserialize_with = "deserialize_mac_key")]
+    pub delta: OtBlock,
+    /// The zero messages of the random OTs
+    #[serde(serialize_with = "serialize_blocks", deserialize_with = "deserialize_blocks")]
+    pub random_m0: Vec<OtBlock>,
+    /// The one messages of the random OTs
+    #[serde(serialize_with = "serialize_blocks", deserialize_with = "deserialize_blocks")]
+    pub random_m1: Vec<OtBlock>,
+    /// The zero messages of the correlated OTs
+    ///
+    /// The one message of each is the zero message XOR delta
+    #[serde(serialize_with = "serialize_blocks", deserialize_with = "deserialize_blocks")]
+    pub correlated_m0: Vec<OtBlock>,
+}
+
+impl OtSenderShares {
+    /// The messages (m0, m1) of the correlated OT at the given index
+    pub fn correlated_messages(&self, index: usize) -> (OtBlock, OtBlock) {
+        let m0 = self.correlated_m0[index];
+        (m0, m0 ^ self.delta)
+    }
+}
+
+/// The receiver's side of the OT correlations in a response
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct OtReceiverShares {
+    /// The choice bits of the random OTs
+    pub random_choices: PackedBits,
+    /// The chosen messages of the random OTs
+    #[serde(serialize_with = "serialize_blocks", deserialize_with = "deserialize_blocks")]
+    pub random_messages: Vec<OtBlock>,
+    /// The choice bits of the correlated OTs
+    pub correlated_choices: PackedBits,
+    /// The chosen messages of the correlated OTs
+    #[serde(serialize_with = "serialize_blocks", deserialize_with = "deserialize_blocks")]
+    pub correlated_messages: Vec<OtBlock>,
+}
+
+/// A party's OT correlations in a response
+///
+/// Only the side matching the party's role is populated, the other is empty
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct OtResponse {
+    /// The sender's side of the correlations
+    pub sender: OtSenderShares,
+    /// The receiver's side of the correlations
+    pub receiver: OtReceiverShares,
+}
+
+impl OtResponse {
+    /// The number of random OTs held by the party
+    pub fn n_random(&self) -> usize {
+        self.sender.random_m0.len().max(self.receiver.random_messages.len())
+    }
+
+    /// The number of correlated OTs held by the party
+    pub fn n_correlated(&self) -> usize {
+        self.sender.correlated_m0.len().max(self.receiver.correlated_messages.len())
+    }
+
+    /// The correlations in the range `start..start + n` of each flavour,
+    /// truncated to the correlations held
+    pub fn slice(&self, start: usize, n: usize) -> Self {
+        let (sender, receiver) = (&self.sender, &self.receiver);
+        let sender_random = clamp(sender.random_m0.len(), start, n);
+        let sender_correlated = clamp(sender.correlated_m0.len(), start, n);
+        let receiver_random = clamp(receiver.random_messages.len(), start, n);
+        let receiver_correlated = clamp(receiver.correlated_messages.len(), start, n);
+
+        Self {
+            sender: OtSenderShares {
+                delta: sender.delta,
+                random_m0: sender.random_m0[sender_random.clone()].to_vec(),
+                random_m1: sender.random_m1[sender_random].to_vec(),
+                correlated_m0: sender.correlated_m0[sender_correlated].to_vec(),
+            },
+            receiver: OtReceiverShares {
+                random_choices: slice_bits(&receiver.random_choices, &receiver_random),
+                random_messages: receiver.random_messages[receiver_random].to_vec(),
+                correlated_choices: slice_bits(&receiver.correlated_choices, &receiver_correlated),
+                correlated_messages: receiver.correlated_messages[receiver_correlated].to_vec(),
+            },
+        }
+    }
+
+    /// Append the correlations of another response to this one
+    pub fn extend(&mut self, other: &Self) {
+        self.sender.random_m0.extend_from_slice(&other.sender.random_m0);
+        self.sender.random_m1.extend_from_slice(&other.sender.random_m1);
+        self.sender.correlated_m0.extend_from_slice(&other.sender.correlated_m0);
+        self.receiver.random_choices.extend(&other.receiver.random_choices);
+        self.receiver.random_messages.extend_from_slice(&other.receiver.random_messages);
+        self.receiver.correlated_choices.extend(&other.receiver.correlated_choices);
+        self.receiver.correlated_messages.extend_from_slice(&other.receiver.correlated_messages);
+    }
+}
+
+/// The bits in the given range
+fn slice_bits(bits: &PackedBits, range: &Range<usize>) -> PackedBits {
+    bits.slice(range.start, range.len())
+}
+
+/// The range `start..start + n` clamped to a section of length `len`
+fn clamp(len: usize, start: usize, n: usize) -> Range<usize> {
+    let start = start.min(len);
+    start..(start + n).min(len)
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{thread_rng, Rng};
+
+    use super::{OtReceiverShares, OtResponse};
+    use crate::binary::PackedBits;
+
+    /// Tests that slicing a response clamps to the correlations held and that
+    /// the encoding round trips
+    #[test]
+    fn test_ot_slice_encoding() {
+        let mut rng = thread_rng();
+        let receiver = OtReceiverShares {
+            random_choices: PackedBits::from_bits((0..5).map(|_| rng.gen_bool(0.5 /* p */))),
+            random_messages: (0..5).map(|_| rng.gen()).collect(),
+            ..Default::default()
+        };
+        let resp = OtResponse { receiver, ..Default::default() };
+
+        let tail = resp.slice(3 /* start */, 4 /* n */);
+        assert_eq!(tail.n_random(), 2);
+        assert_eq!(tail.receiver.random_messages, resp.receiver.random_messages[3..]);
+
+        let ser = serde_json::to_string(&resp).unwrap();
+        let de: OtResponse = serde_json::from_str(&ser).unwrap();
+        assert!(de.receiver.random_choices == resp.receiver.random_choices);
+        assert_eq!(de.receiver.random_messages, resp.receiver.random_messages);
+    }
+}
@@ -0,0 +1,102 @@
+//! Pairwise (BDOZ-style) authenticated shares
+//!
+//! In place of a single global MAC key split between the parties, each party
+//! holds its own MAC key α along with a local key β for each of the
+//! counterparty's shares. A party's share x is authenticated by the MAC
+//! α' * x + β' under the counterparty's keys, so that the counterparty may
+//! check each opening of the share on its own, without a global MAC check.
+//! See Bendlin et al. "Semi-Homomorphic Encryption and Multiparty
+//! Computation"
+
+use serde::{Deserialize, Serialize};
+
+use crate::Scalar;
+
+/// The mode in which a request's values are authenticated
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MacMode {
+    /// SPDZ-style MACs under a global MAC key shared between the parties
+    #[default]
+    Global,
+    /// BDOZ-style MACs under each party's own MAC key
+    ///
+    /// Applies to random bits, random values, and Beaver triples, which are
+    /// dealt in the response's pairwise section; other values keep the global
+    /// MAC key
+    Pairwise,
+}
+
+/// A party's pairwise authenticated share of a value
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PairwiseShare {
+    /// The party's additive share of the value
+    pub share: Scalar,
+    /// The MAC of the party's share under the counterparty's keys
+    pub mac: Scalar,
+    /// The party's local key for the counterparty's share
+    pub key: Scalar,
+}
+
+impl PairwiseShare {
+    /// Check the counterparty's opening of its share against the party's keys
+    ///
+    /// Takes the party's own MAC key, along with the share and MAC opened by
+    /// the counterparty
+    pub fn check_opening(&self, mac_key: Scalar, share: Scalar, mac: Scalar) -> bool {
+        mac == mac_key * share + self.key
+    }
+}
+
+/// A party's pairwise authenticated values in a response
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct PairwiseResponse {
+    /// The party's own MAC key
+    pub mac_key: Scalar,
+    /// The random bits
+    pub random_bits: Vec<PairwiseShare>,
+    /// The random values
+    pub random_values: Vec<PairwiseShare>,
+    /// The Beaver triples
+    pub beaver_triples: (Vec<PairwiseShare>, Vec<PairwiseShare>, Vec<PairwiseShare>),
+}
+
+impl PairwiseResponse {
+    /// Set the random bits
+    pub fn set_random_bits(&mut self, bits: Vec<PairwiseShare>) {
+        self.random_bits = bits;
+    }
+
+    /// Set the random values
+    pub fn set_random_values(&mut self, values: Vec<PairwiseShare>) {
+        self.random_values = values;
+    }
+
+    /// Set the triples
+    pub fn set_triples(
+        &mut self,
+        a: Vec<PairwiseShare>,
+        b: Vec<PairwiseShare>,
+        c: Vec<PairwiseShare>,
+    ) {
+        let n = a.len();
+        assert_eq!(n, b.len());
+        assert_eq!(n, c.len());
+
+        self.beaver_triples = (a, b, c);
+    }
+
+    /// Append the sections of another response to this one
+    pub fn extend(&mut self, other: PairwiseResponse) {
+        self.random_bits.extend(other.random_bits);
+        self.random_values.extend(other.random_values);
+        self.beaver_triples.0.extend(other.beaver_triples.0);
+        self.beaver_triples.1.extend(other.beaver_triples.1);
+        self.beaver_triples.2.extend(other.beaver_triples.2);
+    }
+
+    /// The length of the longest section in the response
+    pub(crate) fn max_section_len(&self) -> usize {
+        self.random_bits.len().max(self.random_values.len()).max(self.beaver_triples.0.len())
+    }
+}
@@ -0,0 +1,440 @@
+//! An implementation of ark-mpc's preprocessing phase backed by the dealer's
+//! response
+//!
+//! Values are consumed in the order the dealer generated them, so both
+//! parties consume matching shares so long as their fabrics request values in
+//! the same order
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use ark_mpc::offline_prep::PreprocessingPhase;
+
+use crate::{
+    binary::{BinaryMacKey, BinaryTriples},
+    Curve, DealerResponse, InnerProductTriple, MatrixTriple, PointShare, PrefixProductTuple,
+    Scalar, ScalarShare,
+};
+
+/// The shares of a batch of Beaver triples, split into `a`, `b`, and `c`
+type TripleShares = (Vec<ScalarShare>, Vec<ScalarShare>, Vec<ScalarShare>);
+/// The shares of a batch of edaBits, split into the values and their bits
+type EdaBitShares = (Vec<ScalarShare>, Vec<Vec<ScalarShare>>);
+
+/// A category of preprocessed values held in a `DealerResponse`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueKind {
+    /// Masks for the local party's inputs
+    LocalInputMasks,
+    /// Shares of the masks for the counterparty's inputs
+    CounterpartyInputMasks,
+    /// Shared random bits
+    RandomBits,
+    /// Shared random values
+    RandomValues,
+    /// Shared pairs of a random value and its inverse
+    InversePairs,
+    /// Beaver triples
+    Triples,
+    /// Shared pairs of a random value and its square
+    SquarePairs,
+    /// Shared pairs of a bounded random value and its truncation
+    TruncationPairs,
+    /// Shared bounded random values along with their bits
+    EdaBits,
+    /// Shared powers of random values
+    PowerTuples,
+    /// Shared tuples for constant-round prefix products
+    PrefixProductTuples,
+    /// Inner-product triples
+    InnerProductTriples,
+    /// Matrix triples
+    MatrixTriples,
+    /// Shared sharings of zero
+    ZeroShares,
+    /// Shared random scalars along with their public points
+    PointShares,
+    /// AND triples over GF(2)
+    BinaryTriples,
+}
+
+/// An error indicating that a category of preprocessed values is exhausted
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExhaustedError {
+    /// The category of values requested
+    pub kind: ValueKind,
+    /// The number of values requested
+    pub requested: usize,
+    /// The number of values remaining
+    pub remaining: usize,
+}
+
+impl Display for ExhaustedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "dealer preprocessing exhausted: requested {} {:?}, {} remaining",
+            self.requested, self.kind, self.remaining
+        )
+    }
+}
+
+impl std::error::Error for ExhaustedError {}
+
+/// A queue of preprocessed values, consumed in order
+struct ValueQueue<T> {
+    /// The values
+    values: Vec<T>,
+    /// The number of values consumed
+    consumed: usize,
+}
+
+impl<T: Clone> ValueQueue<T> {
+    /// Constructor
+    fn new(values: Vec<T>) -> Self {
+        Self { values, consumed: 0 }
+    }
+
+    /// The number of values remaining
+    fn remaining(&self) -> usize {
+        self.values.len() - self.consumed
+    }
+
+    /// Take the next `n` values from the queue
+    fn take(&mut self, n: usize, kind: ValueKind) -> Result<Vec<T>, ExhaustedError> {
+        let remaining = self.remaining();
+        if n > remaining {
+            return Err(ExhaustedError { kind, requested: n, remaining });
+        }
+
+        let values = self.values[self.consumed..self.consumed + n].to_vec();
+        self.consumed += n;
+        Ok(values)
+    }
+}
+
+/// A party's preprocessing, dealt by the dealer
+///
+/// Implements ark-mpc's `PreprocessingPhase`, which is infallible and panics
+/// when a category is exhausted. The `try_next_*` methods return an
+/// `ExhaustedError` instead
+///
+/// Values outside ark-mpc's interface, such as square pairs, edaBits, and
+/// binary triples, are only available through the `try_next_*` methods, and
+/// the circuits using them consume them directly
+pub struct DealerPreprocessing {
+    /// The party's share of the MAC key
+    mac_key_share: Scalar,
+    /// The masks for the local party's inputs, with the party's shares
+    local_input_masks: ValueQueue<(Scalar, ScalarShare)>,
+    /// The party's shares of the masks for the counterparty's inputs
+    counterparty_input_masks: ValueQueue<ScalarShare>,
+    /// The shared random bits
+    random_bits: ValueQueue<ScalarShare>,
+    /// The shared random values
+    random_values: ValueQueue<ScalarShare>,
+    /// The shared inverse pairs
+    inverse_pairs: ValueQueue<(ScalarShare, ScalarShare)>,
+    /// The Beaver triples
+    triples: ValueQueue<(ScalarShare, ScalarShare, ScalarShare)>,
+    /// The shared square pairs
+    square_pairs: ValueQueue<(ScalarShare, ScalarShare)>,
+    /// The shared truncation pairs
+    truncation_pairs: ValueQueue<(ScalarShare, ScalarShare)>,
+    /// The shared edaBits
+    edabits: ValueQueue<(ScalarShare, Vec<ScalarShare>)>,
+    /// The shared powers tuples
+    power_tuples: ValueQueue<Vec<ScalarShare>>,
+    /// The shared prefix-product tuples
+    prefix_product_tuples: ValueQueue<PrefixProductTuple>,
+    /// The inner-product triples
+    inner_product_triples: ValueQueue<InnerProductTriple>,
+    /// The matrix triples
+    matrix_triples: ValueQueue<MatrixTriple>,
+    /// The sharings of zero
+    zero_shares: ValueQueue<ScalarShare>,
+    /// The random points
+    point_shares: ValueQueue<PointShare>,
+    /// The party's share of the binary MAC key
+    binary_mac_key_share: BinaryMacKey,
+    /// The AND triples over GF(2)
+    ///
+    /// Held packed rather than in a `ValueQueue`
+    binary_triples: BinaryTriples,
+    /// The number of AND triples consumed
+    binary_triples_consumed: usize,
+}
+
+impl DealerPreprocessing {
+    /// Constructor
+    ///
+    /// Compressed shares in the response are expanded first
+    pub fn new(mut resp: DealerResponse) -> Self {
+        resp.expand_compressed();
+        let (masks, mask_shares, counterparty_mask_shares) = resp.input_masks;
+        let (r, r_inv) = resp.inverse_pairs;
+        let (a, b, c) = resp.beaver_triples;
+        let triples = a.into_iter().zip(b).zip(c).map(|((a, b), c)| (a, b, c)).collect();
+        let (sq_r, sq_r_squared) = resp.square_pairs;
+        let (trunc_r, trunc_r_shifted) = resp.truncation_pairs;
+        let (edabit_r, edabit_bits) = resp.edabits;
+
+        Self {
+            mac_key_share: resp.mac_key_share,
+            local_input_masks: ValueQueue::new(masks.into_iter().zip(mask_shares).collect()),
+            counterparty_input_masks: ValueQueue::new(counterparty_mask_shares),
+            random_bits: ValueQueue::new(resp.random_bits),
+            random_values: ValueQueue::new(resp.random_values),
+            inverse_pairs: ValueQueue::new(r.into_iter().zip(r_inv).collect()),
+            triples: ValueQueue::new(triples),
+            square_pairs: ValueQueue::new(sq_r.into_iter().zip(sq_r_squared).collect()),
+            truncation_pairs: ValueQueue::new(trunc_r.into_iter().zip(trunc_r_shifted).collect()),
+            edabits: ValueQueue::new(edabit_r.into_iter().zip(edabit_bits).collect()),
+            power_tuples: ValueQueue::new(resp.power_tuples),
+            prefix_product_tuples: ValueQueue::new(resp.prefix_product_tuples),
+            inner_product_triples: ValueQueue::new(resp.inner_product_triples),
+            matrix_triples: ValueQueue::new(resp.matrix_triples),
+            zero_shares: ValueQueue::new(resp.zero_shares),
+            point_shares: ValueQueue::new(resp.point_shares),
+            binary_mac_key_share: resp.binary_mac_key_share,
+            binary_triples: resp.binary_triples,
+            binary_triples_consumed: 0,
+        }
+    }
+
+    /// The number of values of the given kind remaining
+    pub fn remaining(&self, kind: ValueKind) -> usize {
+        match kind {
+            ValueKind::LocalInputMasks => self.local_input_masks.remaining(),
+            ValueKind::CounterpartyInputMasks => self.counterparty_input_masks.remaining(),
+            ValueKind::RandomBits => self.random_bits.remaining(),
+            ValueKind::RandomValues => self.random_values.remaining(),
+            ValueKind::InversePairs => self.inverse_pairs.remaining(),
+            ValueKind::Triples => self.triples.remaining(),
+            ValueKind::SquarePairs => self.square_pairs.remaining(),
+            ValueKind::TruncationPairs => self.truncation_pairs.remaining(),
+            ValueKind::EdaBits => self.edabits.remaining(),
+            ValueKind::PowerTuples => self.power_tuples.remaining(),
+            ValueKind::PrefixProductTuples => self.prefix_product_tuples.remaining(),
+            ValueKind::InnerProductTriples => self.inner_product_triples.remaining(),
+            ValueKind::MatrixTriples => self.matrix_triples.remaining(),
+            ValueKind::ZeroShares => self.zero_shares.remaining(),
+            ValueKind::PointShares => self.point_shares.remaining(),
+            ValueKind::BinaryTriples => self.binary_triples.len() - self.binary_triples_consumed,
+        }
+    }
+
+    /// Take the next `n` masks for the local party's inputs
+    pub fn try_next_local_input_masks(
+        &mut self,
+        n: usize,
+    ) -> Result<(Vec<Scalar>, Vec<ScalarShare>), ExhaustedError> {
+        let masks = self.local_input_masks.take(n, ValueKind::LocalInputMasks)?;
+        Ok(masks.into_iter().unzip())
+    }
+
+    /// Take the next `n` shares of masks for the counterparty's inputs
+    pub fn try_next_counterparty_input_masks(
+        &mut self,
+        n: usize,
+    ) -> Result<Vec<ScalarShare>, ExhaustedError> {
+        self.counterparty_input_masks.take(n, ValueKind::CounterpartyInputMasks)
+    }
+
+    /// Take the next `n` shared random bits
+    pub fn try_next_shared_bits(&mut self, n: usize) -> Result<Vec<ScalarShare>, ExhaustedError> {
+        self.random_bits.take(n, ValueKind::RandomBits)
+    }
+
+    /// Take the next `n` shared random values
+    pub fn try_next_shared_values(&mut self, n: usize) -> Result<Vec<ScalarShare>, ExhaustedError> {
+        self.random_values.take(n, ValueKind::RandomValues)
+    }
+
+    /// Take the next `n` shared inverse pairs
+    pub fn try_next_inverse_pairs(
+        &mut self,
+        n: usize,
+    ) -> Result<(Vec<ScalarShare>, Vec<ScalarShare>), ExhaustedError> {
+        let pairs = self.inverse_pairs.take(n, ValueKind::InversePairs)?;
+        Ok(pairs.into_iter().unzip())
+    }
+
+    /// Take the next `n` Beaver triples
+    pub fn try_next_triples(&mut self, n: usize) -> Result<TripleShares, ExhaustedError> {
+        let triples = self.triples.take(n, ValueKind::Triples)?;
+
+        let mut a = Vec::with_capacity(n);
+        let mut b = Vec::with_capacity(n);
+        let mut c = Vec::with_capacity(n);
+        for (a_share, b_share, c_share) in triples {
+            a.push(a_share);
+            b.push(b_share);
+            c.push(c_share);
+        }
+
+        Ok((a, b, c))
+    }
+
+    /// Take the next `n` shared square pairs
+    pub fn try_next_square_pairs(
+        &mut self,
+        n: usize,
+    ) -> Result<(Vec<ScalarShare>, Vec<ScalarShare>), ExhaustedError> {
+        let pairs = self.square_pairs.take(n, ValueKind::SquarePairs)?;
+        Ok(pairs.into_iter().unzip())
+    }
+
+    /// Take the next `n` shared truncation pairs
+    pub fn try_next_truncation_pairs(
+        &mut self,
+        n: usize,
+    ) -> Result<(Vec<ScalarShare>, Vec<ScalarShare>), ExhaustedError> {
+        let pairs = self.truncation_pairs.take(n, ValueKind::TruncationPairs)?;
+        Ok(pairs.into_iter().unzip())
+    }
+
+    /// Take the next `n` shared edaBits, each a value and its bits
+    pub fn try_next_edabits(&mut self, n: usize) -> Result<EdaBitShares, ExhaustedError> {
+        let edabits = self.edabits.take(n, ValueKind::EdaBits)?;
+        Ok(edabits.into_iter().unzip())
+    }
+
+    /// Take the next `n` shared powers tuples r, ..., r^d of a random r
+    pub fn try_next_power_tuples(
+        &mut self,
+        n: usize,
+    ) -> Result<Vec<Vec<ScalarShare>>, ExhaustedError> {
+        self.power_tuples.take(n, ValueKind::PowerTuples)
+    }
+
+    /// Take the next `n` shared prefix-product tuples
+    pub fn try_next_prefix_product_tuples(
+        &mut self,
+        n: usize,
+    ) -> Result<Vec<PrefixProductTuple>, ExhaustedError> {
+        self.prefix_product_tuples.take(n, ValueKind::PrefixProductTuples)
+    }
+
+    /// Take the next `n` inner-product triples
+    pub fn try_next_inner_product_triples(
+        &mut self,
+        n: usize,
+    ) -> Result<Vec<InnerProductTriple>, ExhaustedError> {
+        self.inner_product_triples.take(n, ValueKind::InnerProductTriples)
+    }
+
+    /// Take the next `n` matrix triples
+    pub fn try_next_matrix_triples(
+        &mut self,
+        n: usize,
+    ) -> Result<Vec<MatrixTriple>, ExhaustedError> {
+        self.matrix_triples.take(n, ValueKind::MatrixTriples)
+    }
+
+    /// Take the next `n` sharings of zero
+    pub fn try_next_zero_shares(&mut self, n: usize) -> Result<Vec<ScalarShare>, ExhaustedError> {
+        self.zero_shares.take(n, ValueKind::ZeroShares)
+    }
+
+    /// Take the next `n` random points
+    pub fn try_next_point_shares(&mut self, n: usize) -> Result<Vec<PointShare>, ExhaustedError> {
+        self.point_shares.take(n, ValueKind::PointShares)
+    }
+
+    /// The party's share of the binary MAC key
+    pub fn binary_mac_key_share(&self) -> BinaryMacKey {
+        self.binary_mac_key_share
+    }
+
+    /// Take the next `n` AND triples over GF(2)
+    pub fn try_next_binary_triples(&mut self, n: usize) -> Result<BinaryTriples, ExhaustedError> {
+        let remaining = self.remaining(ValueKind::BinaryTriples);
+        if n > remaining {
+            return Err(ExhaustedError { kind: ValueKind::BinaryTriples, requested: n, remaining });
+        }
+
+        let triples = self.binary_triples.slice(self.binary_triples_consumed, n);
+        self.binary_triples_consumed += n;
+        Ok(triples)
+    }
+}
+
+impl From<DealerResponse> for DealerPreprocessing {
+    fn from(resp: DealerResponse) -> Self {
+        Self::new(resp)
+    }
+}
+
+/// Unwrap the result of a fallible method for the infallible preprocessing
+/// interface
+fn expect_values<T>(res: Result<T, ExhaustedError>) -> T {
+    res.unwrap_or_else(|err| panic!("{err}"))
+}
+
+impl PreprocessingPhase<Curve> for DealerPreprocessing {
+    fn get_mac_key_share(&self) -> Scalar {
+        self.mac_key_share
+    }
+
+    fn next_local_input_masks(&mut self, num_values: usize) -> (Vec<Scalar>, Vec<ScalarShare>) {
+        expect_values(self.try_next_local_input_masks(num_values))
+    }
+
+    fn next_counterparty_input_masks(&mut self, num_values: usize) -> Vec<ScalarShare> {
+        expect_values(self.try_next_counterparty_input_masks(num_values))
+    }
+
+    fn next_shared_bit_batch(&mut self, num_values: usize) -> Vec<ScalarShare> {
+        expect_values(self.try_next_shared_bits(num_values))
+    }
+
+    fn next_shared_value_batch(&mut self, num_values: usize) -> Vec<ScalarShare> {
+        expect_values(self.try_next_shared_values(num_values))
+    }
+
+    fn next_shared_inverse_pair_batch(
+        &mut self,
+        num_pairs: usize,
+    ) -> (Vec<ScalarShare>, Vec<ScalarShare>) {
+        expect_values(self.try_next_inverse_pairs(num_pairs))
+    }
+
+    fn next_triplet_batch(&mut self, num_triplets: usize) -> TripleShares {
+        expect_values(self.try_next_triples(num_triplets))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ark_mpc::offline_prep::PreprocessingPhase;
+    use rand::thread_rng;
+
+    use super::{DealerPreprocessing, ExhaustedError, ValueKind};
+    use crate::{DealerResponse, Scalar, ScalarShare};
+
+    /// Tests that values are consumed in order and that an exhausted category
+    /// returns an error without consuming values
+    #[test]
+    fn test_consumption_and_exhaustion() {
+        let mut rng = thread_rng();
+        let bits = (0..3)
+            .map(|_| ScalarShare::new(Scalar::random(&mut rng), Scalar::random(&mut rng)))
+            .collect::<Vec<_>>();
+
+        let mut resp = DealerResponse { mac_key_share: Scalar::one(), ..Default::default() };
+        resp.set_random_bits(bits.clone());
+        let mut prep = DealerPreprocessing::from(resp);
+
+        assert_eq!(prep.get_mac_key_share(), Scalar::one());
+        assert_eq!(prep.next_shared_bit(), bits[0]);
+        assert_eq!(prep.next_shared_bit_batch(1), bits[1..2]);
+        assert_eq!(prep.remaining(ValueKind::RandomBits), 1);
+
+        let err = prep.try_next_shared_bits(2).unwrap_err();
+        assert_eq!(err, ExhaustedError { kind: ValueKind::RandomBits, requested: 2, remaining: 1 });
+        assert_eq!(prep.try_next_shared_bits(1).unwrap(), bits[2..]);
+
+        let err = prep.try_next_triples(1).unwrap_err();
+        assert_eq!(err.kind, ValueKind::Triples);
+    }
+}
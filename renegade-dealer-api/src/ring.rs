@@ -0,0 +1,123 @@
+//! SPDZ2k-style preprocessing over the ring Z/2^k
+//!
+//! Values are additively shared over the larger ring Z/2^(k+s), of which only
+//! the low k bits carry the secret, and authenticated with a MAC key α in
+//! Z/2^s: the MAC shares of a value x sum to α * x modulo 2^(k+s). The s upper
+//! bits make forging a MAC as hard as guessing α, see Cramer et al.
+//! "SPDZ2k: Efficient MPC mod 2^k for Dishonest Majority"
+//!
+//! Ring elements are represented as `u128`s, so that k + s is at most 128
+
+use serde::{Deserialize, Serialize};
+
+/// An element of the ring Z/2^(k+s)
+pub type RingElement = u128;
+
+/// The maximum total bit-length k + s of the share ring
+pub const MAX_RING_BIT_LENGTH: u32 = RingElement::BITS;
+
+/// The mask of the low `n_bits` bits of a ring element
+///
+/// Reduces an element modulo 2^n_bits, for `n_bits` between 1 and
+/// `MAX_RING_BIT_LENGTH`
+pub fn ring_mask(n_bits: u32) -> RingElement {
+    assert!(n_bits > 0 && n_bits <= MAX_RING_BIT_LENGTH, "invalid ring bit-length");
+    RingElement::MAX >> (MAX_RING_BIT_LENGTH - n_bits)
+}
+
+/// A party's authenticated share of a ring element
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RingShare {
+    /// The party's additive share of the value modulo 2^(k+s)
+    pub share: RingElement,
+    /// The party's additive share of the MAC modulo 2^(k+s)
+    pub mac: RingElement,
+}
+
+/// A party's share of the SPDZ2k preprocessing in a response
+///
+/// Independent of the field-based values in the rest of the response, and
+/// authenticated under its own MAC key
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RingResponse {
+    /// The bit-length k of the secret values
+    pub bit_length: u32,
+    /// The bit-length s of the MAC key
+    pub security_parameter: u32,
+    /// The party's additive share of the MAC key α modulo 2^(k+s), where
+    /// α < 2^s
+    ///
+    /// Zero when no values over Z/2^k are requested
+    pub mac_key_share: RingElement,
+    /// The random values
+    pub random_values: Vec<RingShare>,
+    /// The random bits
+    pub random_bits: Vec<RingShare>,
+    /// The multiplication triples
+    ///
+    /// Random values a and b and their product c = a * b modulo 2^(k+s)
+    pub triples: (Vec<RingShare>, Vec<RingShare>, Vec<RingShare>),
+}
+
+impl RingResponse {
+    /// The bit-length k + s of the ring over which values are shared
+    pub fn share_bit_length(&self) -> u32 {
+        self.bit_length + self.security_parameter
+    }
+
+    /// Set the random values
+    pub fn set_random_values(&mut self, values: Vec<RingShare>) {
+        self.random_values = values;
+    }
+
+    /// Set the random bits
+    pub fn set_random_bits(&mut self, bits: Vec<RingShare>) {
+        self.random_bits = bits;
+    }
+
+    /// Set the triples
+    pub fn set_triples(&mut self, a: Vec<RingShare>, b: Vec<RingShare>, c: Vec<RingShare>) {
+        let n = a.len();
+        assert_eq!(n, b.len());
+        assert_eq!(n, c.len());
+
+        self.triples = (a, b, c);
+    }
+
+    /// Append the sections of another response to this one
+    pub fn extend(&mut self, other: RingResponse) {
+        self.random_values.extend(other.random_values);
+        self.random_bits.extend(other.random_bits);
+        self.triples.0.extend(other.triples.0);
+        self.triples.1.extend(other.triples.1);
+        self.triples.2.extend(other.triples.2);
+    }
+
+    /// The length of the longest section in the response
+    pub(crate) fn max_section_len(&self) -> usize {
+        self.random_values.len().max(self.random_bits.len()).max(self.triples.0.len())
+    }
+
+    /// An empty response with the same parameters and MAC key share
+    pub(crate) fn empty(&self) -> Self {
+        Self {
+            bit_length: self.bit_length,
+            security_parameter: self.security_parameter,
+            mac_key_share: self.mac_key_share,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ring_mask, MAX_RING_BIT_LENGTH};
+
+    /// Tests the ring masks at the edges of the supported bit-lengths
+    #[test]
+    fn test_ring_mask() {
+        assert_eq!(ring_mask(1), 1);
+        assert_eq!(ring_mask(64), u64::MAX as u128);
+        assert_eq!(ring_mask(MAX_RING_BIT_LENGTH), u128::MAX);
+    }
+}
@@ -0,0 +1,68 @@
+//! Messages exchanged over the dealer's websocket session protocol
+//!
+//! A client opens a websocket for a request ID and sends a `Submit` message.
+//! The server acknowledges the submission, notifies the client when its
+//! counterparty joins, reports generation progress, and streams the party's
+//! shares in chunks. The client may send `Cancel` at any point before the
+//! session completes
+
+use ark_mpc::network::PartyId;
+use serde::{Deserialize, Serialize};
+
+use crate::{DealerRequest, DealerResponse};
+
+/// A message sent by the client over the websocket
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Submit a request to the dealer
+    Submit {
+        /// The ID of the submitting party
+        party_id: PartyId,
+        /// The base64 encoded signature of the serialized request under the
+        /// party's key
+        signature: String,
+        /// The request
+        request: Box<DealerRequest>,
+    },
+    /// Cancel the submitted request
+    Cancel,
+}
+
+/// A message sent by the server over the websocket
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// The request was accepted and is awaiting its counterparty
+    Accepted,
+    /// The counterparty submitted a matching request
+    CounterpartyJoined,
+    /// The dealer's progress generating the request's values
+    Progress {
+        /// The number of values generated so far
+        generated: u32,
+        /// The total number of values to generate
+        total: u32,
+    },
+    /// A chunk of the party's shares
+    ///
+    /// Chunks are sent in order, the full response is recovered by extending
+    /// the first chunk with the rest
+    Chunk {
+        /// The index of the chunk
+        index: u32,
+        /// The total number of chunks
+        n_chunks: u32,
+        /// The shares in the chunk
+        response: Box<DealerResponse>,
+    },
+    /// All chunks were sent, the server closes the session
+    Complete,
+    /// The request was cancelled by either party
+    Cancelled,
+    /// The request failed
+    Error {
+        /// A description of the failure
+        message: String,
+    },
+}
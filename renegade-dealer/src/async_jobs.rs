@@ -0,0 +1,328 @@
+//! Storage for requests submitted asynchronously
+//!
+//! A party that submits asynchronously receives an acknowledgement
+//! immediately and polls for the status of its request. Once generated, the
+//! party's shares are held until they are fetched once or the request expires
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use ark_mpc::network::PartyId;
+use k256::ecdsa::VerifyingKey;
+use renegade_dealer::dealer::{DealerError, DealerEvent, DealerSender, ResponseReceiver};
+use renegade_dealer_api::{DealerResponse, RequestId, RequestStatus};
+use tokio::time::{timeout_at, Instant};
+use tracing::{info, warn, Instrument, Span};
+
+use crate::cancel_request;
+
+/// The key of an async job, a request ID and the party that submitted it
+type JobKey = (RequestId, PartyId);
+
+/// The state of an async job
+enum AsyncJobState {
+    /// The job is awaiting its counterparty
+    Waiting,
+    /// The dealer is generating values for the job
+    Generating,
+    /// The party's shares are ready to be fetched
+    Ready(Box<DealerResponse>),
+    /// The party's shares have been fetched and deleted
+    Fetched,
+    /// The dealer rejected the job
+    Failed(DealerError),
+}
+
+/// A job submitted asynchronously
+struct AsyncJob {
+    /// The key of the submitting party, used to authenticate result fetches
+    key: VerifyingKey,
+    /// The state of the job
+    state: AsyncJobState,
+    /// The time at which the job expires
+    expires_at: Instant,
+}
+
+impl AsyncJob {
+    /// Whether the job has expired
+    fn expired(&self) -> bool {
+        Instant::now() >= self.expires_at
+    }
+}
+
+/// An error fetching the result of an async job
+#[derive(Debug, PartialEq, Eq)]
+pub enum FetchError {
+    /// The job does not exist, expired, failed, or was already fetched
+    NotFound,
+    /// The job's result is not yet generated
+    NotReady,
+}
+
+/// The store of all async jobs
+#[derive(Clone)]
+pub struct AsyncJobStore {
+    /// The jobs, keyed by request ID and party ID
+    jobs: Arc<Mutex<HashMap<JobKey, AsyncJob>>>,
+    /// The duration for which a job is kept after submission
+    ttl: Duration,
+    /// The dealer's queue, on which expired jobs are cancelled
+    dealer_queue: DealerSender,
+}
+
+impl AsyncJobStore {
+    /// Create a new store, spawning a task to prune expired jobs
+    ///
+    /// Expired jobs are kept for an additional `ttl` so that their status may
+    /// still be queried
+    pub fn new(ttl: Duration, dealer_queue: DealerSender) -> Self {
+        let self_ = Self { jobs: Arc::new(Mutex::new(HashMap::new())), ttl, dealer_queue };
+
+        let store = self_.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(store.ttl).await;
+                store.prune();
+            }
+        });
+
+        self_
+    }
+
+    /// Reserve the slot of a job before it is submitted to the dealer
+    ///
+    /// Returns `false` if the party has already submitted the request
+    pub fn reserve(&self, request_id: RequestId, party_id: PartyId, key: VerifyingKey) -> bool {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job_key = (request_id, party_id);
+        if jobs.contains_key(&job_key) {
+            return false;
+        }
+
+        let expires_at = Instant::now() + self.ttl;
+        jobs.insert(job_key, AsyncJob { key, state: AsyncJobState::Waiting, expires_at });
+        true
+    }
+
+    /// Release the slot of a job that was not submitted to the dealer
+    pub fn release(&self, request_id: RequestId, party_id: PartyId) {
+        self.jobs.lock().unwrap().remove(&(request_id, party_id));
+    }
+
+    /// Begin tracking a reserved job from the dealer's events
+    pub fn track(&self, request_id: RequestId, party_id: PartyId, mut recv: ResponseReceiver) {
+        let job_key = (request_id, party_id);
+        let expires_at = match self.jobs.lock().unwrap().get(&job_key) {
+            Some(job) => job.expires_at,
+            None => return,
+        };
+
+        // Follow the dealer's events until the job completes or expires, a job
+        // that expires is cancelled so that the dealer frees its request
+        let store = self.clone();
+        let follow_events = async move {
+            loop {
+                let event = match timeout_at(expires_at, recv.recv()).await {
+                    Ok(Some(event)) => event,
+                    Ok(None) => Err(DealerError::Dropped),
+                    Err(_) => {
+                        info!("async request expired, cancelling");
+                        cancel_request(request_id, party_id, &store.dealer_queue).await;
+                        break;
+                    },
+                };
+
+                let state = match event {
+                    Ok(DealerEvent::Paired | DealerEvent::Progress { .. }) => {
+                        AsyncJobState::Generating
+                    },
+                    Ok(DealerEvent::Response(resp)) => AsyncJobState::Ready(resp),
+                    Err(err) => AsyncJobState::Failed(err),
+                };
+
+                let done = !matches!(state, AsyncJobState::Generating);
+                store.set_state(&job_key, state);
+                if done {
+                    break;
+                }
+            }
+        };
+        tokio::spawn(follow_events.instrument(Span::current()));
+    }
+
+    /// Get the status of a job
+    pub fn status(&self, request_id: RequestId, party_id: PartyId) -> Option<RequestStatus> {
+        let jobs = self.jobs.lock().unwrap();
+        let job = jobs.get(&(request_id, party_id))?;
+        if job.expired() {
+            return Some(RequestStatus::Expired);
+        }
+
+        Some(match &job.state {
+            AsyncJobState::Waiting => RequestStatus::Waiting,
+            AsyncJobState::Generating => RequestStatus::Generating,
+            AsyncJobState::Ready(_) => RequestStatus::Ready,
+            AsyncJobState::Fetched => RequestStatus::Expired,
+            AsyncJobState::Failed(err) => RequestStatus::Failed { message: err.to_string() },
+        })
+    }
+
+    /// Get the key with which a party authenticates result fetches for a job
+    pub fn party_key(&self, request_id: RequestId, party_id: PartyId) -> Option<VerifyingKey> {
+        self.jobs.lock().unwrap().get(&(request_id, party_id)).map(|job| job.key)
+    }
+
+    /// Take the result of a job, deleting it from the store
+    pub fn take_result(
+        &self,
+        request_id: RequestId,
+        party_id: PartyId,
+    ) -> Result<DealerResponse, FetchError> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(&(request_id, party_id)).ok_or(FetchError::NotFound)?;
+        if job.expired() {
+            return Err(FetchError::NotFound);
+        }
+
+        match std::mem::replace(&mut job.state, AsyncJobState::Fetched) {
+            AsyncJobState::Ready(resp) => Ok(*resp),
+            state @ (AsyncJobState::Waiting | AsyncJobState::Generating) => {
+                job.state = state;
+                Err(FetchError::NotReady)
+            },
+            state => {
+                job.state = state;
+                Err(FetchError::NotFound)
+            },
+        }
+    }
+
+    /// Update the state of a job
+    fn set_state(&self, job_key: &JobKey, state: AsyncJobState) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.get_mut(job_key) {
+            match &state {
+                AsyncJobState::Ready(_) => info!("async result ready"),
+                AsyncJobState::Failed(err) => warn!("async request failed: {err}"),
+                _ => {},
+            }
+
+            job.state = state;
+        }
+    }
+
+    /// Remove jobs that expired more than `ttl` ago, dropping any unfetched
+    /// shares
+    fn prune(&self) {
+        let now = Instant::now();
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|_, job| job.expires_at + self.ttl > now);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use ark_mpc::PARTY0;
+    use k256::SecretKey;
+    use rand::thread_rng;
+    use renegade_dealer_api::RequestStatus;
+    use uuid::Uuid;
+
+    use super::{AsyncJobStore, FetchError};
+    use renegade_dealer::dealer::{
+        create_dealer_sender_receiver, create_response_sender_receiver, DealerEvent, DealerMessage,
+    };
+
+    /// Let the store process the events sent to it
+    ///
+    /// The tests run on a single thread with the clock paused, so advancing the
+    /// clock yields to the store's tasks without waiting in real time
+    async fn process_events() {
+        tokio::time::advance(Duration::from_millis(1)).await;
+    }
+
+    /// Tests the lifecycle of an async job through to a single fetch
+    #[tokio::test(start_paused = true)]
+    async fn test_async_job_lifecycle() {
+        let (dealer_send, _dealer_recv) = create_dealer_sender_receiver(1 /* capacity */);
+        let store = AsyncJobStore::new(Duration::from_secs(60), dealer_send);
+        let key = SecretKey::random(&mut thread_rng()).public_key().into();
+        let rid = Uuid::new_v4();
+
+        // Releasing a reservation frees the slot
+        assert!(store.reserve(rid, PARTY0, key));
+        store.release(rid, PARTY0);
+        assert_eq!(store.status(rid, PARTY0), None);
+
+        let (send, recv) = create_response_sender_receiver();
+        assert!(store.reserve(rid, PARTY0, key));
+        store.track(rid, PARTY0, recv);
+        assert_eq!(store.status(rid, PARTY0), Some(RequestStatus::Waiting));
+        assert_eq!(store.take_result(rid, PARTY0).unwrap_err(), FetchError::NotReady);
+
+        send.send(Ok(DealerEvent::Paired)).await.unwrap();
+        process_events().await;
+        assert_eq!(store.status(rid, PARTY0), Some(RequestStatus::Generating));
+
+        send.send(Ok(DealerEvent::Response(Box::default()))).await.unwrap();
+        process_events().await;
+        assert_eq!(store.status(rid, PARTY0), Some(RequestStatus::Ready));
+
+        // The result may only be fetched once
+        assert!(store.take_result(rid, PARTY0).is_ok());
+        assert_eq!(store.take_result(rid, PARTY0).unwrap_err(), FetchError::NotFound);
+        assert_eq!(store.status(rid, PARTY0), Some(RequestStatus::Expired));
+    }
+
+    /// Tests that a job's result is unavailable after it expires
+    #[tokio::test(start_paused = true)]
+    async fn test_async_job_expiry() {
+        const TTL: Duration = Duration::from_secs(60);
+        let (dealer_send, _dealer_recv) = create_dealer_sender_receiver(1 /* capacity */);
+        let store = AsyncJobStore::new(TTL, dealer_send);
+        let key = SecretKey::random(&mut thread_rng()).public_key().into();
+        let rid = Uuid::new_v4();
+
+        let (send, recv) = create_response_sender_receiver();
+        assert!(store.reserve(rid, PARTY0, key));
+        assert!(!store.reserve(rid, PARTY0, key));
+        store.track(rid, PARTY0, recv);
+
+        send.send(Ok(DealerEvent::Response(Box::default()))).await.unwrap();
+        process_events().await;
+        assert_eq!(store.status(rid, PARTY0), Some(RequestStatus::Ready));
+
+        tokio::time::advance(TTL).await;
+
+        assert_eq!(store.status(rid, PARTY0), Some(RequestStatus::Expired));
+        assert_eq!(store.take_result(rid, PARTY0).unwrap_err(), FetchError::NotFound);
+    }
+
+    /// Tests that a job expiring before its result is generated cancels the
+    /// request with the dealer
+    #[tokio::test(start_paused = true)]
+    async fn test_async_job_expiry_cancels() {
+        const TTL: Duration = Duration::from_secs(60);
+        let (dealer_send, mut dealer_recv) = create_dealer_sender_receiver(1 /* capacity */);
+        let store = AsyncJobStore::new(TTL, dealer_send);
+        let key = SecretKey::random(&mut thread_rng()).public_key().into();
+        let rid = Uuid::new_v4();
+
+        let (_send, recv) = create_response_sender_receiver();
+        assert!(store.reserve(rid, PARTY0, key));
+        store.track(rid, PARTY0, recv);
+        tokio::time::advance(TTL).await;
+
+        let msg = dealer_recv.recv().await.unwrap();
+        assert!(matches!(
+            msg,
+            DealerMessage::Cancel { request_id, party_id } if request_id == rid && party_id == PARTY0
+        ));
+        assert_eq!(store.status(rid, PARTY0), Some(RequestStatus::Expired));
+    }
+}
@@ -6,49 +6,192 @@
 //! The dealer aggregates requests between matching parties and generates
 //! offline phase results
 
-use ark_mpc::network::PartyId;
-use itertools::Itertools;
+use ark_mpc::{network::PartyId, PARTY0};
+use itertools::{izip, Itertools};
 use rand::{thread_rng, Rng};
 use std::{
     collections::HashMap,
-    sync::{Arc, Mutex},
+    fmt::{Display, Formatter, Result as FmtResult},
+    iter::successors,
+    mem::take,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
-use tokio::sync::mpsc::{
-    unbounded_channel, UnboundedReceiver as Receiver, UnboundedSender as Sender,
+use tokio::sync::{
+    mpsc::{channel, Receiver, Sender},
+    Semaphore,
+};
+use tracing::{info, info_span, warn, Span};
+
+use k256::PublicKey;
+use renegade_dealer_api::{
+    binary::{BinaryMacKey, BinaryShares, BinaryTriples, PackedBits},
+    compression::CompressedShares,
+    ot::{OtBlock, OtReceiverShares, OtResponse, OtSenderShares},
+    pairwise::{MacMode, PairwiseResponse, PairwiseShare},
+    ring::{ring_mask, RingElement, RingShare},
+    DealerRequest, DealerResponse, InnerProductTriple, MatrixTriple, PointShare,
+    PrefixProductTuple, Profiles, RequestCounts, RequestId, SessionId,
 };
-
-use renegade_dealer_api::{DealerRequest, DealerResponse, RequestId};
 use uuid::Uuid;
 
-use crate::BadRequestError;
+use crate::{
+    metrics::{
+        record_values_dealt, GENERATION_TIME, JOB_QUEUE_LENGTH, OPEN_REQUESTS, OPEN_SESSIONS,
+        PAIRINGS, PAIRING_WAIT_TIME,
+    },
+    pool::{PoolConfig, PreprocessingPool},
+};
 
 // ---------
 // | Types |
 // ---------
 
+/// The maximum number of values that may be requested at once by a pair
+pub const MAX_REQUEST_SIZE: u32 = 1_500_000;
+
 /// The curve that the server generates scalars for
 type Curve = ark_bn254::G1Projective;
 /// A type alias for a scalar over the correct curve
 type Scalar = ark_mpc::algebra::Scalar<Curve>;
 /// A type alias for a scalar share over the correct curve
 type ScalarShare = ark_mpc::algebra::ScalarShare<Curve>;
+/// A type alias for a point on the correct curve
+type CurvePoint = ark_mpc::algebra::CurvePoint<Curve>;
 
 /// A sender to the Dealer's queue
-pub type DealerSender = Sender<DealerJob>;
+pub type DealerSender = Sender<DealerMessage>;
 /// A receiver from the Dealer's queue
-pub type DealerReceiver = Receiver<DealerJob>;
-/// Create a new sender and receiver
-pub fn create_dealer_sender_receiver() -> (DealerSender, DealerReceiver) {
-    unbounded_channel()
+pub type DealerReceiver = Receiver<DealerMessage>;
+/// Create a new sender and receiver with the given queue capacity
+pub fn create_dealer_sender_receiver(capacity: usize) -> (DealerSender, DealerReceiver) {
+    channel(capacity)
 }
 
 /// The response channel sender from the dealer
-pub type ResponseSender = Sender<Result<DealerResponse, BadRequestError>>;
+pub type ResponseSender = Sender<Result<DealerEvent, DealerError>>;
 /// The response channel receiver from the dealer
-pub type ResponseReceiver = Receiver<Result<DealerResponse, BadRequestError>>;
+pub type ResponseReceiver = Receiver<Result<DealerEvent, DealerError>>;
 /// Create a new sender and receiver
+///
+/// Progress events are dropped when the buffer is full, so the channel need
+/// only buffer a handful of messages
 pub fn create_response_sender_receiver() -> (ResponseSender, ResponseReceiver) {
-    unbounded_channel()
+    channel(8 /* buffer */)
+}
+
+/// The two parties' shares of the MAC keys for a batch
+#[derive(Clone, Copy)]
+pub(crate) struct MacKeyShares {
+    /// The shares of the MAC key over the scalar field
+    scalar: (Scalar, Scalar),
+    /// The shares of the MAC key over GF(2^128), for binary values
+    binary: (BinaryMacKey, BinaryMacKey),
+    /// The MAC key over Z/2^s and the first party's share of it, for values
+    /// over Z/2^k
+    ///
+    /// Both are sampled at full width, as the key is reduced modulo 2^s and
+    /// the share modulo 2^(k+s) for each request. See `ring_key_shares`
+    ring: (RingElement, RingElement),
+    /// Each party's own MAC key, for values with pairwise MACs
+    pairwise: (Scalar, Scalar),
+}
+
+impl MacKeyShares {
+    /// The ring MAC key and the parties' additive shares of it for the given
+    /// bit-length k and security parameter s
+    ///
+    /// The key is reduced modulo 2^s, and the shares sum to it modulo 2^(k+s)
+    fn ring_key_shares(
+        &self,
+        bit_length: u32,
+        security_parameter: u32,
+    ) -> (RingElement, RingElement, RingElement) {
+        let (key, share1) = self.ring;
+        let share_mask = ring_mask(bit_length + security_parameter);
+        let key = key & ring_mask(security_parameter);
+        let share1 = share1 & share_mask;
+
+        (key, share1, key.wrapping_sub(share1) & share_mask)
+    }
+}
+
+/// A function generating one type of correlated randomness for a pair
+type GenFn = fn(&RequestCounts, Scalar, &mut DealerResponse, &mut DealerResponse);
+
+/// A message on the Dealer's queue
+pub enum DealerMessage {
+    /// A job submitted by a party
+    Job(Box<DealerJob>),
+    /// A party's cancellation of its request
+    Cancel {
+        /// The ID of the cancelled request
+        request_id: RequestId,
+        /// The party cancelling the request
+        party_id: PartyId,
+    },
+}
+
+impl From<DealerJob> for DealerMessage {
+    fn from(job: DealerJob) -> Self {
+        DealerMessage::Job(Box::new(job))
+    }
+}
+
+/// An event sent by the dealer to a job's requester
+#[derive(Clone, Debug)]
+pub enum DealerEvent {
+    /// The counterparty has arrived and generation is scheduled
+    Paired,
+    /// The dealer's progress generating values for the pair
+    Progress {
+        /// The number of values generated so far
+        generated: u32,
+        /// The total number of values to generate
+        total: u32,
+    },
+    /// The requester's shares of the generated values
+    Response(Box<DealerResponse>),
+}
+
+/// Await the response to a job, skipping any intermediate events
+pub async fn await_response(recv: &mut ResponseReceiver) -> Result<DealerResponse, DealerError> {
+    loop {
+        match recv.recv().await.ok_or(DealerError::Dropped)?? {
+            DealerEvent::Paired | DealerEvent::Progress { .. } => continue,
+            DealerEvent::Response(resp) => return Ok(*resp),
+        }
+    }
+}
+
+/// An error returned by the dealer in response to a job
+#[derive(Clone, Debug)]
+pub enum DealerError {
+    /// The request was invalid, on its own or given its counterparty's request
+    BadRequest(&'static str),
+    /// The dealer is at capacity and cannot accept the job
+    Saturated,
+    /// The request was cancelled by one of the parties
+    Cancelled,
+    /// The request expired before its counterparty arrived
+    Expired,
+    /// The dealer dropped the request without responding
+    Dropped,
+}
+
+impl Display for DealerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            DealerError::BadRequest(msg) => write!(f, "{msg}"),
+            DealerError::Saturated => write!(f, "Dealer saturated"),
+            DealerError::Cancelled => write!(f, "Request cancelled"),
+            DealerError::Expired => write!(f, "Request expired"),
+            DealerError::Dropped => write!(f, "Dealer dropped the request"),
+        }
+    }
 }
 
 /// The job received by a Dealer to handle a pair of requests
@@ -61,17 +204,130 @@ pub struct DealerJob {
     pub request: DealerRequest,
     /// The channel on which to respond
     pub chan: ResponseSender,
+    /// The time at which the job was created
+    pub received_at: Instant,
+    /// The tracing span of the request that created the job
+    pub span: Span,
 }
 
 impl DealerJob {
     /// Constructor
+    ///
+    /// Captures the current tracing span so that the dealer's logs for the job
+    /// are attributed to the originating request
     pub fn new(
         request_id: RequestId,
         party_id: PartyId,
         request: DealerRequest,
         chan: ResponseSender,
     ) -> Self {
-        Self { request_id, party_id, request, chan }
+        let received_at = Instant::now();
+        Self { request_id, party_id, request, chan, received_at, span: Span::current() }
+    }
+
+    /// Send an event to the job's requester without blocking
+    ///
+    /// The requester may have disconnected, in which case the event is dropped.
+    /// The event is also dropped if the channel is full, which may only happen
+    /// to progress events
+    fn respond(&self, event: Result<DealerEvent, DealerError>) {
+        let _ = self.chan.try_send(event);
+    }
+
+    /// Send an event to the job's requester, blocking until the channel has
+    /// capacity
+    ///
+    /// Must only be called from the blocking thread pool
+    fn respond_blocking(&self, event: Result<DealerEvent, DealerError>) {
+        let _ = self.chan.blocking_send(event);
+    }
+}
+
+/// The configuration of the dealer's resource limits
+#[derive(Clone, Debug)]
+pub struct DealerConfig {
+    /// The maximum number of jobs buffered in the dealer's queue
+    pub queue_capacity: usize,
+    /// The maximum number of requests awaiting their counterparty
+    pub max_open_requests: usize,
+    /// The duration for which a request awaits its counterparty before it
+    /// expires
+    pub open_request_ttl: Duration,
+    /// The maximum number of pairs for which generation runs concurrently
+    pub max_concurrent_jobs: usize,
+    /// The duration for which a session's MAC key is kept after it is
+    /// established
+    pub session_ttl: Duration,
+    /// The configuration of the pool of pre-generated preprocessing
+    pub pool: PoolConfig,
+    /// The named request profiles
+    pub profiles: Profiles,
+}
+
+impl DealerConfig {
+    /// Fill in the counts of a request that references a profile
+    ///
+    /// A request referencing a profile may not request values of its own, and
+    /// the scaled counts are subject to the same size limit as explicit counts
+    pub fn resolve_profile(&self, req: &mut DealerRequest) -> Result<(), DealerError> {
+        let Some(profile) = &req.profile else { return Ok(()) };
+        if req.total_values() > 0 {
+            return Err(DealerError::BadRequest("Request sets both a profile and counts"));
+        }
+
+        let counts =
+            self.profiles.get(&profile.name).ok_or(DealerError::BadRequest("Unknown profile"))?;
+        req.counts = counts
+            .checked_mul(profile.multiplier)
+            .filter(|counts| counts.total_values() <= MAX_REQUEST_SIZE)
+            .ok_or(DealerError::BadRequest("Request size too large"))?;
+
+        req.counts.validate().map_err(DealerError::BadRequest)
+    }
+}
+
+impl Default for DealerConfig {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 1024,
+            max_open_requests: 1024,
+            open_request_ttl: Duration::from_secs(300),
+            max_concurrent_jobs: 4,
+            session_ttl: Duration::from_secs(3600),
+            pool: PoolConfig::default(),
+            profiles: Profiles::new(),
+        }
+    }
+}
+
+/// A session between two parties, all batches in which are authenticated
+/// under the same MAC key
+struct Session {
+    /// The key of the first party
+    first_party_key: PublicKey,
+    /// The key of the second party
+    second_party_key: PublicKey,
+    /// The parties' shares of the session's MAC key
+    mac_key_shares: MacKeyShares,
+    /// The time at which the session expires
+    expires_at: Instant,
+}
+
+/// Sample fresh MAC keys, split into shares for the two parties
+pub(crate) fn sample_mac_key_shares() -> MacKeyShares {
+    let mut rng = thread_rng();
+    let mac_key = Scalar::random(&mut rng);
+    let mac_share1 = Scalar::random(&mut rng);
+
+    // Shares of the binary key are XOR shares
+    let binary_key: BinaryMacKey = rng.gen();
+    let binary_share1: BinaryMacKey = rng.gen();
+
+    MacKeyShares {
+        scalar: (mac_share1, mac_key - mac_share1),
+        binary: (binary_share1, binary_key ^ binary_share1),
+        ring: (rng.gen(), rng.gen()),
+        pairwise: (Scalar::random(&mut rng), Scalar::random(&mut rng)),
     }
 }
 
@@ -87,72 +343,470 @@ pub struct Dealer {
     ///
     /// Maps request ID to the request
     pub open_requests: Arc<Mutex<HashMap<Uuid, DealerJob>>>,
+    /// The cancellation flags of the pairs being generated or awaiting a
+    /// generation permit
+    generating: Arc<Mutex<HashMap<RequestId, Arc<AtomicBool>>>>,
+    /// The open sessions
+    sessions: Arc<Mutex<HashMap<SessionId, Session>>>,
+    /// The pool of pre-generated preprocessing, if enabled
+    pool: Option<Arc<PreprocessingPool>>,
+    /// The permits bounding the number of concurrent generation jobs
+    generation_permits: Arc<Semaphore>,
+    /// The dealer's resource limits
+    config: DealerConfig,
 }
 
 impl Dealer {
+    /// Generate the correlated randomness for a request, returning the
+    /// shares of the first and second party respectively
+    ///
+    /// Generation runs on the calling thread, no pairing is involved. The
+    /// request's counts are used as given, a request referencing a profile
+    /// must first be resolved with `DealerConfig::resolve_profile`. The counts
+    /// are validated as those of a request to the service
+    pub fn deal(req: &DealerRequest) -> Result<(DealerResponse, DealerResponse), DealerError> {
+        if req.total_values() > MAX_REQUEST_SIZE {
+            return Err(DealerError::BadRequest("Request size too large"));
+        }
+        req.counts.validate().map_err(DealerError::BadRequest)?;
+
+        let resps = Self::generate(&req.counts, sample_mac_key_shares(), |_| true);
+        Ok(resps.expect("generation without cancellation"))
+    }
+
+    /// The named request profiles configured on the dealer
+    pub fn profiles(&self) -> &Profiles {
+        &self.config.profiles
+    }
+
     /// Start a dealer implementation
-    pub fn start(job_queue: DealerReceiver) {
-        let self_ = Self { open_requests: Arc::new(Mutex::new(HashMap::new())) };
+    ///
+    /// Jobs submitted on the queue are paired by request ID and each party is
+    /// sent its shares on the job's response channel. Must be called from
+    /// within a tokio runtime
+    ///
+    /// Returns a handle to the dealer's shared state
+    pub fn start(config: DealerConfig, job_queue: DealerReceiver) -> Self {
+        let self_ = Self {
+            open_requests: Arc::new(Mutex::new(HashMap::new())),
+            generating: Arc::new(Mutex::new(HashMap::new())),
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            pool: config.pool.enabled().then(|| PreprocessingPool::start(&config.pool)),
+            generation_permits: Arc::new(Semaphore::new(config.max_concurrent_jobs)),
+            config,
+        };
+
+        let handle = self_.clone();
         tokio::spawn(async move {
             self_.run(job_queue).await;
         });
+
+        handle
     }
 
     /// Main loop
+    ///
+    /// Pairing is handled inline, generation is delegated to the blocking
+    /// thread pool. Each pair awaits a generation permit in its own task, so
+    /// that the loop keeps pairing and handling cancellations while all
+    /// permits are taken
     async fn run(self, mut job_queue: DealerReceiver) {
-        loop {
-            let request = job_queue.recv().await.unwrap();
-            let self_ = self.clone();
+        while let Some(message) = job_queue.recv().await {
+            JOB_QUEUE_LENGTH.dec();
+            let request = match message {
+                DealerMessage::Job(request) => *request,
+                DealerMessage::Cancel { request_id, party_id } => {
+                    self.handle_cancel(request_id, party_id);
+                    continue;
+                },
+            };
+            let Some((req1, req2, mac_key_shares)) = self.handle_request(request) else {
+                continue;
+            };
+
+            let cancelled = Arc::new(AtomicBool::new(false));
+            self.generating.lock().unwrap().insert(req1.request_id, cancelled.clone());
+
+            let permits = self.generation_permits.clone();
+            let generating = self.generating.clone();
             tokio::spawn(async move {
-                self_.handle_request(request);
+                let permit = permits.acquire_owned().await.unwrap();
+                tokio::task::spawn_blocking(move || {
+                    Self::handle_ready_pair(&req1, &req2, mac_key_shares, &cancelled);
+                    generating.lock().unwrap().remove(&req1.request_id);
+                    drop(permit);
+                });
             });
         }
     }
 
+    /// Handle a party's cancellation of its request
+    ///
+    /// A request awaiting its counterparty is removed, a pair being generated
+    /// is abandoned at the next progress update. Messages on the queue are
+    /// handled in order, so a cancellation never overtakes its request
+    fn handle_cancel(&self, request_id: RequestId, party_id: PartyId) {
+        let mut open_requests = self.open_requests.lock().unwrap();
+        if open_requests.get(&request_id).is_some_and(|job| job.party_id == party_id) {
+            let job = open_requests.remove(&request_id).unwrap();
+            OPEN_REQUESTS.set(open_requests.len() as i64);
+
+            let _enter = job.span.clone().entered();
+            info!("request cancelled before pairing");
+            job.respond(Err(DealerError::Cancelled));
+        } else if let Some(cancelled) = self.generating.lock().unwrap().get(&request_id) {
+            info!(request_id = %request_id, party_id, "cancelling generation");
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Close a session, returning whether the session was open
+    pub fn close_session(&self, session_id: SessionId) -> bool {
+        let mut sessions = self.sessions.lock().unwrap();
+        let closed = sessions.remove(&session_id).is_some();
+        OPEN_SESSIONS.set(sessions.len() as i64);
+
+        closed
+    }
+
+    /// Get the key of a party to an open session
+    pub fn session_party_key(&self, session_id: SessionId, party_id: PartyId) -> Option<PublicKey> {
+        let sessions = self.sessions.lock().unwrap();
+        let session = sessions.get(&session_id).filter(|s| s.expires_at > Instant::now())?;
+        Some(if party_id == PARTY0 { session.first_party_key } else { session.second_party_key })
+    }
+
+    /// Get the MAC key of the session a request belongs to
+    ///
+    /// Returns `None` if the request belongs to no session or establishes a
+    /// new session, in which case the pair's values may use any MAC key. A
+    /// request referencing an existing session must come from the same
+    /// parties that established it
+    fn session_mac_key(
+        &self,
+        request_id: RequestId,
+        req: &DealerRequest,
+    ) -> Result<Option<MacKeyShares>, DealerError> {
+        let Some(session_id) = req.session_id else { return Ok(None) };
+
+        let now = Instant::now();
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, session| session.expires_at > now);
+        OPEN_SESSIONS.set(sessions.len() as i64);
+
+        if session_id == request_id {
+            if sessions.contains_key(&session_id) {
+                return Err(DealerError::BadRequest("Session already exists"));
+            }
+
+            return Ok(None);
+        }
+
+        match sessions.get(&session_id) {
+            Some(session)
+                if session.first_party_key == req.first_party_key
+                    && session.second_party_key == req.second_party_key =>
+            {
+                Ok(Some(session.mac_key_shares))
+            },
+            _ => Err(DealerError::BadRequest("Unknown session")),
+        }
+    }
+
+    /// Establish a session under the given MAC key
+    fn establish_session(
+        &self,
+        session_id: SessionId,
+        req: &DealerRequest,
+        mac_key_shares: MacKeyShares,
+    ) {
+        info!(session_id = %session_id, "establishing session");
+        let session = Session {
+            first_party_key: req.first_party_key,
+            second_party_key: req.second_party_key,
+            mac_key_shares,
+            expires_at: Instant::now() + self.config.session_ttl,
+        };
+
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.insert(session_id, session);
+        OPEN_SESSIONS.set(sessions.len() as i64);
+    }
+
     /// Handle a request
-    fn handle_request(&self, request: DealerJob) {
+    ///
+    /// Returns the pair of jobs and the MAC key under which to generate their
+    /// values if the request completes a pair
+    fn handle_request(
+        &self,
+        mut request: DealerJob,
+    ) -> Option<(DealerJob, DealerJob, MacKeyShares)> {
+        let _enter = request.span.clone().entered();
+        if let Err(err) = self.config.resolve_profile(&mut request.request) {
+            warn!("invalid profile: {err}");
+            request.respond(Err(err));
+            return None;
+        }
+
         // Lock the requests
         let id = request.request_id;
         let mut open_requests = self.open_requests.lock().unwrap();
+        self.prune_open_requests(&mut open_requests);
         if let Some(existing_req) = open_requests.remove(&id) {
-            assert_eq!(existing_req.request, request.request);
-
-            // Requests should be from different parties
-            if existing_req.party_id == request.party_id {
-                let err = BadRequestError("Duplicate party ID");
-                request.chan.send(Err(err.clone())).unwrap();
-                existing_req.chan.send(Err(err)).unwrap();
-                return;
+            OPEN_REQUESTS.set(open_requests.len() as i64);
+
+            // Requests should be identical and from different parties. This check
+            // runs on the dealer's main loop, so it must reject rather than panic
+            let err = if existing_req.request.profile != request.request.profile {
+                Some(DealerError::BadRequest("Mismatched profiles"))
+            } else if existing_req.request != request.request {
+                Some(DealerError::BadRequest("Mismatched requests"))
+            } else if existing_req.party_id == request.party_id {
+                Some(DealerError::BadRequest("Duplicate party ID"))
+            } else {
+                None
+            };
+
+            if let Some(err) = err {
+                warn!("invalid request pair: {err:?}");
+                request.respond(Err(err.clone()));
+                existing_req.respond(Err(err));
+                return None;
+            }
+
+            let wait_time = existing_req.received_at.elapsed();
+            info!(
+                counterparty = existing_req.party_id,
+                "paired with counterparty after {wait_time:?}"
+            );
+            PAIRINGS.inc();
+            PAIRING_WAIT_TIME.observe(wait_time.as_secs_f64());
+
+            let session_key = match self.session_mac_key(id, &request.request) {
+                Ok(key) => key,
+                Err(err) => {
+                    warn!("invalid session: {err}");
+                    request.respond(Err(err.clone()));
+                    existing_req.respond(Err(err));
+                    return None;
+                },
+            };
+
+            existing_req.respond(Ok(DealerEvent::Paired));
+            request.respond(Ok(DealerEvent::Paired));
+
+            // Pairs outside an existing session may be served from the pool
+            let req = &request.request;
+            let establishes_session = req.session_id == Some(id);
+            let pooled = self.pool.as_ref().filter(|_| session_key.is_none());
+            if let Some((resp1, resp2, pool_key_shares)) =
+                pooled.and_then(|pool| pool.take(&req.counts))
+            {
+                if establishes_session {
+                    self.establish_session(id, req, pool_key_shares);
+                }
+
+                info!("served pair from preprocessing pool");
+                record_values_dealt(&req.counts);
+                existing_req.respond(Ok(DealerEvent::Response(Box::new(resp1))));
+                request.respond(Ok(DealerEvent::Response(Box::new(resp2))));
+                return None;
+            }
+
+            // Bound the pairs awaiting a generation permit by the queue capacity
+            let max_pairs = self.config.max_concurrent_jobs + self.config.queue_capacity;
+            if self.generating.lock().unwrap().len() >= max_pairs {
+                warn!("too many pairs awaiting generation, rejecting");
+                request.respond(Err(DealerError::Saturated));
+                existing_req.respond(Err(DealerError::Saturated));
+                return None;
+            }
+
+            let mac_key_shares = session_key.unwrap_or_else(sample_mac_key_shares);
+            if establishes_session {
+                self.establish_session(id, req, mac_key_shares);
             }
 
-            Self::handle_ready_pair(&existing_req, &request);
+            Some((existing_req, request, mac_key_shares))
+        } else if open_requests.len() >= self.config.max_open_requests {
+            warn!("too many open requests, rejecting");
+            request.respond(Err(DealerError::Saturated));
+            None
         } else {
+            info!("awaiting counterparty");
             open_requests.insert(id, request);
+            OPEN_REQUESTS.set(open_requests.len() as i64);
+            None
         }
     }
 
+    /// Remove the open requests that have expired or whose requester has
+    /// disconnected, freeing their slots
+    fn prune_open_requests(&self, open_requests: &mut HashMap<Uuid, DealerJob>) {
+        let ttl = self.config.open_request_ttl;
+        open_requests.retain(|_, job| {
+            if job.chan.is_closed() {
+                let _enter = job.span.enter();
+                info!("requester disconnected before pairing");
+                return false;
+            }
+
+            if job.received_at.elapsed() >= ttl {
+                let _enter = job.span.enter();
+                info!("request expired before pairing");
+                job.respond(Err(DealerError::Expired));
+                return false;
+            }
+
+            true
+        });
+        OPEN_REQUESTS.set(open_requests.len() as i64);
+    }
+
     /// Handle a pair of requests that are ready for setup
-    fn handle_ready_pair(req1: &DealerJob, req2: &DealerJob) {
-        let mut rng = thread_rng();
+    fn handle_ready_pair(
+        req1: &DealerJob,
+        req2: &DealerJob,
+        mac_key_shares: MacKeyShares,
+        cancelled: &AtomicBool,
+    ) {
         let req = &req1.request;
+        let total = req.total_values();
+        let span = info_span!(parent: &req2.span, "generation", n_values = total);
+        span.follows_from(&req1.span);
+        let _enter = span.entered();
+
+        let timer = GENERATION_TIME.start_timer();
+        let on_progress = |generated| {
+            let event = DealerEvent::Progress { generated, total };
+            req1.respond(Ok(event.clone()));
+            req2.respond(Ok(event));
+            !cancelled.load(Ordering::Relaxed)
+        };
+
+        let Some((resp1, resp2)) = Self::generate(&req.counts, mac_key_shares, on_progress) else {
+            timer.stop_and_discard();
+            info!("generation cancelled");
+            req1.respond_blocking(Err(DealerError::Cancelled));
+            req2.respond_blocking(Err(DealerError::Cancelled));
+            return;
+        };
+
+        let elapsed = timer.stop_and_record();
+        record_values_dealt(&req.counts);
+        info!("generated preprocessing in {elapsed:.3}s");
+
+        req1.respond_blocking(Ok(DealerEvent::Response(Box::new(resp1))));
+        req2.respond_blocking(Ok(DealerEvent::Response(Box::new(resp2))));
+    }
 
-        // Generate the mac key
-        let mac_key = Scalar::random(&mut rng);
-        let mac_share1 = Scalar::random(&mut rng);
-        let mac_share2 = mac_key - mac_share1;
-
-        let mut resp1 = DealerResponse { mac_key_share: mac_share1, ..Default::default() };
-        let mut resp2 = DealerResponse { mac_key_share: mac_share2, ..Default::default() };
+    /// Generate the correlated randomness for the given counts
+    ///
+    /// The values are authenticated under the MAC keys with the given shares.
+    /// `on_progress` is called with the number of values generated after each
+    /// type of value, generation is abandoned if it returns `false`
+    pub(crate) fn generate(
+        counts: &RequestCounts,
+        mac_key_shares: MacKeyShares,
+        mut on_progress: impl FnMut(u32) -> bool,
+    ) -> Option<(DealerResponse, DealerResponse)> {
+        let (mac_share1, mac_share2) = mac_key_shares.scalar;
+        let (binary_share1, binary_share2) = mac_key_shares.binary;
+        let (pairwise_key1, pairwise_key2) = mac_key_shares.pairwise;
+        let mac_key = mac_share1 + mac_share2;
+        let mut resp1 = DealerResponse {
+            mac_key_share: mac_share1,
+            binary_mac_key_share: binary_share1,
+            pairwise: PairwiseResponse { mac_key: pairwise_key1, ..Default::default() },
+            ..Default::default()
+        };
+        let mut resp2 = DealerResponse {
+            mac_key_share: mac_share2,
+            binary_mac_key_share: binary_share2,
+            pairwise: PairwiseResponse { mac_key: pairwise_key2, ..Default::default() },
+            ..Default::default()
+        };
+
+        // In pairwise mode, the values with pairwise MACs are dealt separately
+        let (counts, pairwise_counts) = match counts.mac_mode {
+            MacMode::Global => (*counts, RequestCounts::default()),
+            MacMode::Pairwise => (
+                RequestCounts { n_random_bits: 0, n_random_values: 0, n_triples: 0, ..*counts },
+                *counts,
+            ),
+        };
+        let counts = &counts;
 
         // Setup the values
-        Self::gen_random_bits(req.n_random_bits as usize, mac_key, &mut resp1, &mut resp2);
-        Self::gen_random_values(req.n_random_values as usize, mac_key, &mut resp1, &mut resp2);
-        Self::gen_input_masks(req.n_input_masks as usize, mac_key, &mut resp1, &mut resp2);
-        Self::gen_inverse_pairs(req.n_inverse_pairs as usize, mac_key, &mut resp1, &mut resp2);
-        Self::gen_triples(req.n_triples as usize, mac_key, &mut resp1, &mut resp2);
+        let sections: [(u32, GenFn); 14] = [
+            (counts.n_random_bits, Self::gen_random_bits),
+            (counts.n_random_values, Self::gen_random_values),
+            (
+                counts
+                    .n_input_masks
+                    .saturating_add(counts.n_first_party_input_masks)
+                    .saturating_add(counts.n_second_party_input_masks),
+                Self::gen_input_masks,
+            ),
+            (counts.n_inverse_pairs, Self::gen_inverse_pairs),
+            (counts.n_triples, Self::gen_triples),
+            (counts.n_square_pairs, Self::gen_square_pairs),
+            (counts.n_truncation_pairs, Self::gen_truncation_pairs),
+            (counts.n_edabit_values(), Self::gen_edabits),
+            (counts.n_power_tuple_values(), Self::gen_power_tuples),
+            (counts.n_prefix_product_values(), Self::gen_prefix_product_tuples),
+            (counts.n_inner_product_values(), Self::gen_inner_product_triples),
+            (counts.n_matrix_values(), Self::gen_matrix_triples),
+            (counts.n_zero_shares, Self::gen_zero_shares),
+            (counts.n_point_shares, Self::gen_point_shares),
+        ];
+
+        let mut generated: u32 = 0;
+        for (n, gen) in sections {
+            gen(counts, mac_key, &mut resp1, &mut resp2);
+            generated = generated.saturating_add(n);
+            if !on_progress(generated) {
+                return None;
+            }
+        }
+
+        // Binary values are authenticated under their own key
+        let binary_key = binary_share1 ^ binary_share2;
+        Self::gen_binary_triples(counts, binary_key, &mut resp1, &mut resp2);
+        generated = generated.saturating_add(counts.n_binary_triples);
+        if !on_progress(generated) {
+            return None;
+        }
+
+        // As are values over Z/2^k
+        Self::gen_ring_values(counts, &mac_key_shares, &mut resp1, &mut resp2);
+        generated = generated.saturating_add(counts.n_ring_values());
+        if !on_progress(generated) {
+            return None;
+        }
+
+        // OT correlations are unauthenticated
+        Self::gen_ots(counts, &mut resp1, &mut resp2);
+        generated =
+            generated.saturating_add(counts.n_random_ots).saturating_add(counts.n_correlated_ots);
+        if !on_progress(generated) {
+            return None;
+        }
+
+        let pairwise_keys = (pairwise_key1, pairwise_key2);
+        Self::gen_pairwise_values(&pairwise_counts, pairwise_keys, &mut resp1, &mut resp2);
+        let n_pairwise = pairwise_counts
+            .n_random_bits
+            .saturating_add(pairwise_counts.n_random_values)
+            .saturating_add(pairwise_counts.n_triples);
+        if !on_progress(generated.saturating_add(n_pairwise)) {
+            return None;
+        }
 
-        req1.chan.send(Ok(resp1)).unwrap();
-        req2.chan.send(Ok(resp2)).unwrap();
+        if counts.compressed {
+            Self::compress_shares(&mut resp1, &mut resp2);
+        }
+        Some((resp1, resp2))
     }
 
     // ------------------------------------
@@ -163,11 +817,12 @@ impl Dealer {
     ///
     /// I.e. shares of values in {0, 1}
     fn gen_random_bits(
-        n: usize,
+        counts: &RequestCounts,
         mac_key: Scalar,
         resp1: &mut DealerResponse,
         resp2: &mut DealerResponse,
     ) {
+        let n = counts.n_random_bits as usize;
         let mut rng = thread_rng();
         let bits = (0..n).map(|_| Scalar::from(rng.gen_bool(0.5 /* p */))).collect_vec();
         let (share1, share2) = Self::gen_authenticated_secret_shares(mac_key, &bits);
@@ -178,11 +833,12 @@ impl Dealer {
 
     /// Setup the shared random values of the response
     fn gen_random_values(
-        n: usize,
+        counts: &RequestCounts,
         mac_key: Scalar,
         resp1: &mut DealerResponse,
         resp2: &mut DealerResponse,
     ) {
+        let n = counts.n_random_values as usize;
         let mut rng = thread_rng();
         let values = (0..n).map(|_| Scalar::random(&mut rng)).collect_vec();
         let (share1, share2) = Self::gen_authenticated_secret_shares(mac_key, &values);
@@ -192,15 +848,18 @@ impl Dealer {
     }
 
     /// Generate input masks for the response
+    ///
+    /// Each party may be given a different number of masks
     fn gen_input_masks(
-        n: usize,
+        counts: &RequestCounts,
         mac_key: Scalar,
         resp1: &mut DealerResponse,
         resp2: &mut DealerResponse,
     ) {
         let mut rng = thread_rng();
-        let masks1 = (0..n).map(|_| Scalar::random(&mut rng)).collect_vec();
-        let masks2 = (0..n).map(|_| Scalar::random(&mut rng)).collect_vec();
+        let (n1, n2) = counts.party_input_masks();
+        let masks1 = (0..n1).map(|_| Scalar::random(&mut rng)).collect_vec();
+        let masks2 = (0..n2).map(|_| Scalar::random(&mut rng)).collect_vec();
 
         let (mask1_share1, mask1_share2) = Self::gen_authenticated_secret_shares(mac_key, &masks1);
         let (mask2_share1, mask2_share2) = Self::gen_authenticated_secret_shares(mac_key, &masks2);
@@ -211,11 +870,12 @@ impl Dealer {
 
     /// Setup the inverse pairs of the response
     fn gen_inverse_pairs(
-        n: usize,
+        counts: &RequestCounts,
         mac_key: Scalar,
         resp1: &mut DealerResponse,
         resp2: &mut DealerResponse,
     ) {
+        let n = counts.n_inverse_pairs as usize;
         let mut rng = thread_rng();
         let r = (0..n).map(|_| Scalar::random(&mut rng)).collect_vec();
         let r_inv = r.iter().map(|r| r.inverse()).collect_vec();
@@ -231,11 +891,12 @@ impl Dealer {
     ///
     /// These are vectors of values a, b, c such that a * b = c
     fn gen_triples(
-        n: usize,
+        counts: &RequestCounts,
         mac_key: Scalar,
         resp1: &mut DealerResponse,
         resp2: &mut DealerResponse,
     ) {
+        let n = counts.n_triples as usize;
         let mut rng = thread_rng();
         let a = (0..n).map(|_| Scalar::random(&mut rng)).collect_vec();
         let b = (0..n).map(|_| Scalar::random(&mut rng)).collect_vec();
@@ -249,21 +910,593 @@ impl Dealer {
         resp2.set_triples(a_shares2, b_shares2, c_shares2);
     }
 
+    /// Setup the square pairs of the response
+    ///
+    /// I.e. shares of random values r and r^2, which let a party square a
+    /// value with a single opening rather than a full triple
+    fn gen_square_pairs(
+        counts: &RequestCounts,
+        mac_key: Scalar,
+        resp1: &mut DealerResponse,
+        resp2: &mut DealerResponse,
+    ) {
+        let n = counts.n_square_pairs as usize;
+        let mut rng = thread_rng();
+        let r = (0..n).map(|_| Scalar::random(&mut rng)).collect_vec();
+        let r_squared = r.iter().map(|r| r * r).collect_vec();
+
+        let (r_shares1, r_shares2) = Self::gen_authenticated_secret_shares(mac_key, &r);
+        let (r_sq_shares1, r_sq_shares2) =
+            Self::gen_authenticated_secret_shares(mac_key, &r_squared);
+
+        resp1.set_square_pairs(r_shares1, r_sq_shares1);
+        resp2.set_square_pairs(r_shares2, r_sq_shares2);
+    }
+
+    /// Setup the truncation pairs of the response
+    ///
+    /// I.e. shares of a random value r of the requested bit-length and of
+    /// r >> k for the requested shift k. The value is sampled as its high and
+    /// low bits so that the shifted value is known without reducing r
+    fn gen_truncation_pairs(
+        counts: &RequestCounts,
+        mac_key: Scalar,
+        resp1: &mut DealerResponse,
+        resp2: &mut DealerResponse,
+    ) {
+        let n = counts.n_truncation_pairs as usize;
+        let shift = counts.truncation_shift;
+        let high_bits = counts.truncation_bit_length.saturating_sub(shift);
+        let scale = Scalar::from(2u64).pow(shift as u64);
+
+        let mut rng = thread_rng();
+        let r_shifted = (0..n).map(|_| Self::random_bounded(high_bits, &mut rng)).collect_vec();
+        let r = r_shifted
+            .iter()
+            .map(|r_high| r_high * scale + Self::random_bounded(shift, &mut rng))
+            .collect_vec();
+
+        let (r_shares1, r_shares2) = Self::gen_authenticated_secret_shares(mac_key, &r);
+        let (r_shifted_shares1, r_shifted_shares2) =
+            Self::gen_authenticated_secret_shares(mac_key, &r_shifted);
+
+        resp1.set_truncation_pairs(r_shares1, r_shifted_shares1);
+        resp2.set_truncation_pairs(r_shares2, r_shifted_shares2);
+    }
+
+    /// Setup the edaBits of the response
+    ///
+    /// I.e. shares of a random value r < 2^k for the requested bit-length k,
+    /// along with shares of each of its bits
+    fn gen_edabits(
+        counts: &RequestCounts,
+        mac_key: Scalar,
+        resp1: &mut DealerResponse,
+        resp2: &mut DealerResponse,
+    ) {
+        let n = counts.n_edabits as usize;
+        let k = counts.edabit_bit_length as usize;
+        let mut rng = thread_rng();
+
+        let mut r_shares1 = Vec::with_capacity(n);
+        let mut r_shares2 = Vec::with_capacity(n);
+        let mut bit_shares1 = Vec::with_capacity(n);
+        let mut bit_shares2 = Vec::with_capacity(n);
+        for _ in 0..n {
+            let bits = (0..k).map(|_| Scalar::from(rng.gen_bool(0.5 /* p */))).collect_vec();
+            let r = bits.iter().rev().fold(Scalar::zero(), |acc, bit| acc + acc + bit);
+
+            let (r_share1, r_share2) = Self::gen_authenticated_secret_shares(mac_key, &[r]);
+            let (bits1, bits2) = Self::gen_authenticated_secret_shares(mac_key, &bits);
+            r_shares1.extend(r_share1);
+            r_shares2.extend(r_share2);
+            bit_shares1.push(bits1);
+            bit_shares2.push(bits2);
+        }
+
+        resp1.set_edabits(r_shares1, bit_shares1);
+        resp2.set_edabits(r_shares2, bit_shares2);
+    }
+
+    /// Setup the powers tuples of the response
+    ///
+    /// I.e. shares of a random value r and its powers up to the requested
+    /// degree, which let a party evaluate a public polynomial on a secret
+    /// input after a single opening
+    fn gen_power_tuples(
+        counts: &RequestCounts,
+        mac_key: Scalar,
+        resp1: &mut DealerResponse,
+        resp2: &mut DealerResponse,
+    ) {
+        let n = counts.n_power_tuples as usize;
+        let degree = counts.power_degree as usize;
+        let mut rng = thread_rng();
+
+        let mut tuples1 = Vec::with_capacity(n);
+        let mut tuples2 = Vec::with_capacity(n);
+        for _ in 0..n {
+            let r = Scalar::random(&mut rng);
+            let powers = successors(Some(r), |power| Some(power * r)).take(degree).collect_vec();
+
+            let (shares1, shares2) = Self::gen_authenticated_secret_shares(mac_key, &powers);
+            tuples1.push(shares1);
+            tuples2.push(shares2);
+        }
+
+        resp1.set_power_tuples(tuples1);
+        resp2.set_power_tuples(tuples2);
+    }
+
+    /// Setup the prefix-product tuples of the response
+    ///
+    /// I.e. shares of random values r_1, ..., r_l for the requested length l,
+    /// of their inverses, and of the adjacent products r_{i+1} * r_i^-1
+    fn gen_prefix_product_tuples(
+        counts: &RequestCounts,
+        mac_key: Scalar,
+        resp1: &mut DealerResponse,
+        resp2: &mut DealerResponse,
+    ) {
+        let n = counts.n_prefix_product_tuples as usize;
+        let length = counts.prefix_product_length as usize;
+        let mut rng = thread_rng();
+
+        let mut tuples1 = Vec::with_capacity(n);
+        let mut tuples2 = Vec::with_capacity(n);
+        for _ in 0..n {
+            let r = (0..length).map(|_| Scalar::random(&mut rng)).collect_vec();
+            let r_inv = r.iter().map(|r| r.inverse()).collect_vec();
+            let adjacent =
+                r[1..].iter().zip(r_inv.iter()).map(|(r, r_inv)| r * r_inv).collect_vec();
+
+            let (r_shares1, r_shares2) = Self::gen_authenticated_secret_shares(mac_key, &r);
+            let (r_inv_shares1, r_inv_shares2) =
+                Self::gen_authenticated_secret_shares(mac_key, &r_inv);
+            let (adjacent_shares1, adjacent_shares2) =
+                Self::gen_authenticated_secret_shares(mac_key, &adjacent);
+
+            tuples1.push(PrefixProductTuple {
+                r: r_shares1,
+                r_inv: r_inv_shares1,
+                adjacent: adjacent_shares1,
+            });
+            tuples2.push(PrefixProductTuple {
+                r: r_shares2,
+                r_inv: r_inv_shares2,
+                adjacent: adjacent_shares2,
+            });
+        }
+
+        resp1.set_prefix_product_tuples(tuples1);
+        resp2.set_prefix_product_tuples(tuples2);
+    }
+
+    /// Setup the inner-product triples of the response
+    ///
+    /// I.e. shares of random vectors a and b of the requested length, and of
+    /// their inner product c = <a, b>
+    fn gen_inner_product_triples(
+        counts: &RequestCounts,
+        mac_key: Scalar,
+        resp1: &mut DealerResponse,
+        resp2: &mut DealerResponse,
+    ) {
+        let n = counts.n_inner_product_triples as usize;
+        let length = counts.inner_product_length as usize;
+        let mut rng = thread_rng();
+
+        let mut triples1 = Vec::with_capacity(n);
+        let mut triples2 = Vec::with_capacity(n);
+        for _ in 0..n {
+            let a = (0..length).map(|_| Scalar::random(&mut rng)).collect_vec();
+            let b = (0..length).map(|_| Scalar::random(&mut rng)).collect_vec();
+            let c: Scalar = a.iter().zip(b.iter()).map(|(a, b)| a * b).sum();
+
+            let (a_shares1, a_shares2) = Self::gen_authenticated_secret_shares(mac_key, &a);
+            let (b_shares1, b_shares2) = Self::gen_authenticated_secret_shares(mac_key, &b);
+            let (c_shares1, c_shares2) = Self::gen_authenticated_secret_shares(mac_key, &[c]);
+
+            triples1.push(InnerProductTriple { a: a_shares1, b: b_shares1, c: c_shares1[0] });
+            triples2.push(InnerProductTriple { a: a_shares2, b: b_shares2, c: c_shares2[0] });
+        }
+
+        resp1.set_inner_product_triples(triples1);
+        resp2.set_inner_product_triples(triples2);
+    }
+
+    /// Setup the matrix triples of the response
+    ///
+    /// I.e. shares of random matrices A and B of the requested dimensions, and
+    /// of their product C = AB, each in row-major order
+    fn gen_matrix_triples(
+        counts: &RequestCounts,
+        mac_key: Scalar,
+        resp1: &mut DealerResponse,
+        resp2: &mut DealerResponse,
+    ) {
+        let n = counts.n_matrix_triples as usize;
+        let rows = counts.matrix_rows as usize;
+        let inner = counts.matrix_inner as usize;
+        let cols = counts.matrix_cols as usize;
+        let mut rng = thread_rng();
+
+        let mut triples1 = Vec::with_capacity(n);
+        let mut triples2 = Vec::with_capacity(n);
+        for _ in 0..n {
+            let a = (0..rows * inner).map(|_| Scalar::random(&mut rng)).collect_vec();
+            let b = (0..inner * cols).map(|_| Scalar::random(&mut rng)).collect_vec();
+            let c = (0..rows)
+                .cartesian_product(0..cols)
+                .map(|(i, j)| (0..inner).map(|l| a[i * inner + l] * b[l * cols + j]).sum())
+                .collect_vec();
+
+            let (a_shares1, a_shares2) = Self::gen_authenticated_secret_shares(mac_key, &a);
+            let (b_shares1, b_shares2) = Self::gen_authenticated_secret_shares(mac_key, &b);
+            let (c_shares1, c_shares2) = Self::gen_authenticated_secret_shares(mac_key, &c);
+
+            triples1.push(MatrixTriple { a: a_shares1, b: b_shares1, c: c_shares1 });
+            triples2.push(MatrixTriple { a: a_shares2, b: b_shares2, c: c_shares2 });
+        }
+
+        resp1.set_matrix_triples(triples1);
+        resp2.set_matrix_triples(triples2);
+    }
+
+    /// Setup the zero sharings of the response
+    ///
+    /// I.e. authenticated shares of zero, which a party adds to its shares to
+    /// re-randomize them before revealing them. The sharing is generated for
+    /// any number of parties, the dealer currently serves two
+    fn gen_zero_shares(
+        counts: &RequestCounts,
+        mac_key: Scalar,
+        resp1: &mut DealerResponse,
+        resp2: &mut DealerResponse,
+    ) {
+        let zeros = vec![Scalar::zero(); counts.n_zero_shares as usize];
+        let [shares1, shares2] = Self::gen_n_party_authenticated_shares(mac_key, &zeros);
+
+        resp1.set_zero_shares(shares1);
+        resp2.set_zero_shares(shares2);
+    }
+
+    /// Setup the random points of the response
+    ///
+    /// I.e. shares of random scalars r, each with the public point r * G
+    fn gen_point_shares(
+        counts: &RequestCounts,
+        mac_key: Scalar,
+        resp1: &mut DealerResponse,
+        resp2: &mut DealerResponse,
+    ) {
+        let mut rng = thread_rng();
+        let r = (0..counts.n_point_shares).map(|_| Scalar::random(&mut rng)).collect_vec();
+        let commitments = r.iter().map(|r| CurvePoint::generator() * r).collect_vec();
+        let (r1, r2) = Self::gen_authenticated_secret_shares(mac_key, &r);
+
+        let points = |shares: Vec<ScalarShare>| {
+            shares
+                .into_iter()
+                .zip(commitments.iter())
+                .map(|(r, commitment)| PointShare { r, commitment: *commitment })
+                .collect_vec()
+        };
+        resp1.set_point_shares(points(r1));
+        resp2.set_point_shares(points(r2));
+    }
+
+    /// Setup the AND triples over GF(2) of the response
+    ///
+    /// I.e. XOR shares of random bits a and b, and of c = a AND b, each
+    /// authenticated under the binary MAC key
+    fn gen_binary_triples(
+        counts: &RequestCounts,
+        binary_key: BinaryMacKey,
+        resp1: &mut DealerResponse,
+        resp2: &mut DealerResponse,
+    ) {
+        let n = counts.n_binary_triples as usize;
+        let mut rng = thread_rng();
+        let a = PackedBits::from_bits((0..n).map(|_| rng.gen_bool(0.5 /* p */)));
+        let b = PackedBits::from_bits((0..n).map(|_| rng.gen_bool(0.5 /* p */)));
+        let c = a.and(&b);
+
+        let (a_shares1, a_shares2) = Self::gen_authenticated_binary_shares(binary_key, &a);
+        let (b_shares1, b_shares2) = Self::gen_authenticated_binary_shares(binary_key, &b);
+        let (c_shares1, c_shares2) = Self::gen_authenticated_binary_shares(binary_key, &c);
+
+        resp1.set_binary_triples(BinaryTriples { a: a_shares1, b: b_shares1, c: c_shares1 });
+        resp2.set_binary_triples(BinaryTriples { a: a_shares2, b: b_shares2, c: c_shares2 });
+    }
+
+    /// Setup the SPDZ2k-style values over Z/2^k of the response
+    ///
+    /// Values are shared modulo 2^(k+s) and authenticated under the ring MAC
+    /// key reduced modulo 2^s, whose shares are dealt modulo 2^(k+s)
+    fn gen_ring_values(
+        counts: &RequestCounts,
+        mac_key_shares: &MacKeyShares,
+        resp1: &mut DealerResponse,
+        resp2: &mut DealerResponse,
+    ) {
+        for resp in [&mut *resp1, &mut *resp2] {
+            resp.ring.bit_length = counts.ring_bit_length;
+            resp.ring.security_parameter = counts.ring_security_parameter;
+        }
+
+        if counts.n_ring_values() == 0 {
+            return;
+        }
+
+        let (ring_key, key_share1, key_share2) =
+            mac_key_shares.ring_key_shares(counts.ring_bit_length, counts.ring_security_parameter);
+        resp1.ring.mac_key_share = key_share1;
+        resp2.ring.mac_key_share = key_share2;
+
+        let mut rng = thread_rng();
+        let share_mask = ring_mask(resp1.ring.share_bit_length());
+        let mut random_elements =
+            |n: u32| (0..n).map(|_| rng.gen::<RingElement>() & share_mask).collect_vec();
+
+        let values = random_elements(counts.n_ring_random_values);
+        let (values1, values2) = Self::gen_authenticated_ring_shares(ring_key, share_mask, &values);
+        resp1.ring.set_random_values(values1);
+        resp2.ring.set_random_values(values2);
+
+        let a = random_elements(counts.n_ring_triples);
+        let b = random_elements(counts.n_ring_triples);
+        let c = a.iter().zip(b.iter()).map(|(a, b)| a.wrapping_mul(*b) & share_mask).collect_vec();
+        let (a1, a2) = Self::gen_authenticated_ring_shares(ring_key, share_mask, &a);
+        let (b1, b2) = Self::gen_authenticated_ring_shares(ring_key, share_mask, &b);
+        let (c1, c2) = Self::gen_authenticated_ring_shares(ring_key, share_mask, &c);
+        resp1.ring.set_triples(a1, b1, c1);
+        resp2.ring.set_triples(a2, b2, c2);
+
+        let bits = (0..counts.n_ring_random_bits).map(|_| rng.gen::<bool>() as RingElement);
+        let (bits1, bits2) =
+            Self::gen_authenticated_ring_shares(ring_key, share_mask, &bits.collect_vec());
+        resp1.ring.set_random_bits(bits1);
+        resp2.ring.set_random_bits(bits2);
+    }
+
+    /// Setup the OT correlations of the response
+    ///
+    /// The party given by the request's `ot_sender` receives the sender's side
+    /// of the correlations, the delta of the correlated OTs is fresh for each
+    /// request
+    fn gen_ots(counts: &RequestCounts, resp1: &mut DealerResponse, resp2: &mut DealerResponse) {
+        let mut rng = thread_rng();
+        let mut random_blocks = |n: u32| (0..n).map(|_| rng.gen::<OtBlock>()).collect_vec();
+        let random_m0 = random_blocks(counts.n_random_ots);
+        let random_m1 = random_blocks(counts.n_random_ots);
+        let correlated_m0 = random_blocks(counts.n_correlated_ots);
+
+        let delta = rng.gen::<OtBlock>();
+        let random_choices =
+            PackedBits::from_bits((0..counts.n_random_ots).map(|_| rng.gen_bool(0.5 /* p */)));
+        let correlated_choices =
+            PackedBits::from_bits((0..counts.n_correlated_ots).map(|_| rng.gen_bool(0.5 /* p */)));
+
+        let random_messages = random_choices
+            .iter()
+            .zip(random_m0.iter().zip(random_m1.iter()))
+            .map(|(b, (m0, m1))| if b { *m1 } else { *m0 })
+            .collect();
+        let correlated_messages = correlated_choices
+            .iter()
+            .zip(correlated_m0.iter())
+            .map(|(b, m0)| if b { m0 ^ delta } else { *m0 })
+            .collect();
+
+        let (sender, receiver) =
+            if counts.ot_sender == PARTY0 { (resp1, resp2) } else { (resp2, resp1) };
+        sender.set_ot(OtResponse {
+            sender: OtSenderShares { delta, random_m0, random_m1, correlated_m0 },
+            ..Default::default()
+        });
+        receiver.set_ot(OtResponse {
+            receiver: OtReceiverShares {
+                random_choices,
+                random_messages,
+                correlated_choices,
+                correlated_messages,
+            },
+            ..Default::default()
+        });
+    }
+
+    /// Setup the values with pairwise MACs of the response
+    ///
+    /// I.e. random bits, random values, and Beaver triples, each share
+    /// authenticated under the counterparty's MAC key
+    fn gen_pairwise_values(
+        counts: &RequestCounts,
+        (mac_key1, mac_key2): (Scalar, Scalar),
+        resp1: &mut DealerResponse,
+        resp2: &mut DealerResponse,
+    ) {
+        let mut rng = thread_rng();
+        let gen_shares =
+            |values: &[Scalar]| Self::gen_pairwise_authenticated_shares(mac_key1, mac_key2, values);
+
+        let bits = (0..counts.n_random_bits).map(|_| Scalar::from(rng.gen_bool(0.5 /* p */)));
+        let (bits1, bits2) = gen_shares(&bits.collect_vec());
+        resp1.pairwise.set_random_bits(bits1);
+        resp2.pairwise.set_random_bits(bits2);
+
+        let values = (0..counts.n_random_values).map(|_| Scalar::random(&mut rng)).collect_vec();
+        let (values1, values2) = gen_shares(&values);
+        resp1.pairwise.set_random_values(values1);
+        resp2.pairwise.set_random_values(values2);
+
+        let a = (0..counts.n_triples).map(|_| Scalar::random(&mut rng)).collect_vec();
+        let b = (0..counts.n_triples).map(|_| Scalar::random(&mut rng)).collect_vec();
+        let c = a.iter().zip(b.iter()).map(|(a, b)| a * b).collect_vec();
+        let (a1, a2) = gen_shares(&a);
+        let (b1, b2) = gen_shares(&b);
+        let (c1, c2) = gen_shares(&c);
+        resp1.pairwise.set_triples(a1, b1, c1);
+        resp2.pairwise.set_triples(a2, b2, c2);
+    }
+
+    /// Compress the first party's random bits, random values, and Beaver
+    /// triples into a seed
+    ///
+    /// The second party's shares are replaced by corrections, such that they
+    /// sum with the shares expanded from the seed to the original values and
+    /// MACs
+    fn compress_shares(resp1: &mut DealerResponse, resp2: &mut DealerResponse) {
+        let compressed = CompressedShares {
+            seed: thread_rng().gen(),
+            n_random_bits: resp1.random_bits.len() as u32,
+            n_random_values: resp1.random_values.len() as u32,
+            n_triples: resp1.beaver_triples.0.len() as u32,
+        };
+        let expanded = compressed.expand();
+
+        let correct =
+            |shares1: Vec<ScalarShare>, shares2: &mut [ScalarShare], seeded: Vec<ScalarShare>| {
+                for (share1, share2, seeded) in izip!(shares1, shares2, seeded) {
+                    *share2 = ScalarShare::new(
+                        share1.share() + share2.share() - seeded.share(),
+                        share1.mac() + share2.mac() - seeded.mac(),
+                    );
+                }
+            };
+
+        let (a, b, c) = expanded.beaver_triples;
+        correct(take(&mut resp1.random_bits), &mut resp2.random_bits, expanded.random_bits);
+        correct(take(&mut resp1.random_values), &mut resp2.random_values, expanded.random_values);
+        correct(take(&mut resp1.beaver_triples.0), &mut resp2.beaver_triples.0, a);
+        correct(take(&mut resp1.beaver_triples.1), &mut resp2.beaver_triples.1, b);
+        correct(take(&mut resp1.beaver_triples.2), &mut resp2.beaver_triples.2, c);
+        resp1.compressed = Some(compressed);
+    }
+
     // -----------
     // | Helpers |
     // -----------
 
+    /// Generate pairwise authenticated shares of a given set of values
+    ///
+    /// Each party's share x is authenticated by the MAC α * x + β, where α is
+    /// the counterparty's MAC key and β the counterparty's local key for the
+    /// share
+    fn gen_pairwise_authenticated_shares(
+        mac_key1: Scalar,
+        mac_key2: Scalar,
+        values: &[Scalar],
+    ) -> (Vec<PairwiseShare>, Vec<PairwiseShare>) {
+        let [shares1, shares2] = Self::gen_secret_shares(values);
+        let mut rng = thread_rng();
+        shares1
+            .into_iter()
+            .zip(shares2)
+            .map(|(share1, share2)| {
+                let key1 = Scalar::random(&mut rng);
+                let key2 = Scalar::random(&mut rng);
+                (
+                    PairwiseShare { share: share1, mac: mac_key2 * share1 + key2, key: key1 },
+                    PairwiseShare { share: share2, mac: mac_key1 * share2 + key1, key: key2 },
+                )
+            })
+            .unzip()
+    }
+
+    /// Generate authenticated additive shares of elements of Z/2^(k+s)
+    ///
+    /// `share_mask` reduces modulo 2^(k+s), the MAC of a value x is α * x for
+    /// the ring MAC key α
+    fn gen_authenticated_ring_shares(
+        ring_key: RingElement,
+        share_mask: RingElement,
+        values: &[RingElement],
+    ) -> (Vec<RingShare>, Vec<RingShare>) {
+        let mut rng = thread_rng();
+        values
+            .iter()
+            .map(|value| {
+                let mac = ring_key.wrapping_mul(*value) & share_mask;
+                let share1 = RingShare {
+                    share: rng.gen::<RingElement>() & share_mask,
+                    mac: rng.gen::<RingElement>() & share_mask,
+                };
+                let share2 = RingShare {
+                    share: value.wrapping_sub(share1.share) & share_mask,
+                    mac: mac.wrapping_sub(share1.mac) & share_mask,
+                };
+
+                (share1, share2)
+            })
+            .unzip()
+    }
+
+    /// Generate authenticated XOR shares of a given set of bits
+    ///
+    /// The MAC of a bit x is x * Δ for the binary MAC key Δ, i.e. the key
+    /// itself or zero
+    fn gen_authenticated_binary_shares(
+        binary_key: BinaryMacKey,
+        bits: &PackedBits,
+    ) -> (BinaryShares, BinaryShares) {
+        let mut rng = thread_rng();
+        let share1 = PackedBits::from_bits((0..bits.len()).map(|_| rng.gen_bool(0.5 /* p */)));
+        let share2 = bits.xor(&share1);
+
+        let macs = bits.iter().map(|bit| if bit { binary_key } else { 0 });
+        let mac_shares1 = (0..bits.len()).map(|_| rng.gen::<BinaryMacKey>()).collect_vec();
+        let mac_shares2 = macs.zip(mac_shares1.iter()).map(|(mac, share)| mac ^ share).collect();
+
+        (
+            BinaryShares { bits: share1, macs: mac_shares1 },
+            BinaryShares { bits: share2, macs: mac_shares2 },
+        )
+    }
+
+    /// Sample a uniformly random scalar of at most `n_bits` bits
+    ///
+    /// The scalar is built from 64-bit limbs, most significant first, with the
+    /// first limb truncated to the remaining bits
+    fn random_bounded(n_bits: u32, rng: &mut impl Rng) -> Scalar {
+        let limb_base = Scalar::from(1u128 << 64);
+        let n_limbs = n_bits.div_ceil(64);
+
+        let mut value = Scalar::zero();
+        for i in 0..n_limbs {
+            let limb_bits = if i == 0 { n_bits - 64 * (n_limbs - 1) } else { 64 };
+            let limb = rng.gen::<u64>() >> (64 - limb_bits);
+            value = value * limb_base + Scalar::from(limb);
+        }
+
+        value
+    }
+
     /// Generate authenticated secret shares of a given set of values
     fn gen_authenticated_secret_shares(
         mac_key: Scalar,
         values: &[Scalar],
     ) -> (Vec<ScalarShare>, Vec<ScalarShare>) {
+        let [shares1, shares2] = Self::gen_n_party_authenticated_shares(mac_key, values);
+        (shares1, shares2)
+    }
+
+    /// Generate authenticated secret shares of a given set of values for `N`
+    /// parties
+    fn gen_n_party_authenticated_shares<const N: usize>(
+        mac_key: Scalar,
+        values: &[Scalar],
+    ) -> [Vec<ScalarShare>; N] {
         let macs = Self::compute_macs(mac_key, values);
-        let (shares1, shares2) = Self::gen_secret_shares(values);
-        let (mac_shares1, mac_shares2) = Self::gen_secret_shares(&macs);
+        let shares = Self::gen_secret_shares::<N>(values);
+        let mac_shares = Self::gen_secret_shares::<N>(&macs);
 
         // Collect into shares
-        (Self::collect_shares(&shares1, &mac_shares1), Self::collect_shares(&shares2, &mac_shares2))
+        let mut parties = shares.iter().zip(mac_shares.iter());
+        std::array::from_fn(|_| {
+            let (shares, mac_shares) = parties.next().unwrap();
+            Self::collect_shares(shares, mac_shares)
+        })
     }
 
     /// Compute the macs of a set of values
@@ -271,18 +1504,23 @@ impl Dealer {
         values.iter().map(|v| v * mac_key).collect_vec()
     }
 
-    /// Generate secret shares of a given set of values
-    fn gen_secret_shares(values: &[Scalar]) -> (Vec<Scalar>, Vec<Scalar>) {
+    /// Generate secret shares of a given set of values for `N` parties
+    ///
+    /// The first `N - 1` shares of each value are random, the last is chosen
+    /// so that the shares sum to the value
+    fn gen_secret_shares<const N: usize>(values: &[Scalar]) -> [Vec<Scalar>; N] {
         let mut rng = thread_rng();
-        let mut share1 = Vec::with_capacity(values.len());
-        let mut share2 = Vec::with_capacity(values.len());
+        let mut shares: [Vec<Scalar>; N] =
+            std::array::from_fn(|_| Vec::with_capacity(values.len()));
         for value in values {
-            let share = Scalar::random(&mut rng);
-            share1.push(share);
-            share2.push(value - share);
+            let random = (0..N - 1).map(|_| Scalar::random(&mut rng)).collect_vec();
+            let last = *value - random.iter().copied().sum::<Scalar>();
+            for (party_shares, share) in shares.iter_mut().zip(random.into_iter().chain([last])) {
+                party_shares.push(share);
+            }
         }
 
-        (share1, share2)
+        shares
     }
 
     /// Collect a set of values and macs into a vector of shares
@@ -298,18 +1536,52 @@ impl Dealer {
 
 #[cfg(test)]
 mod test {
+    use std::time::Duration;
+
     use ark_mpc::{PARTY0, PARTY1};
     use itertools::{izip, Itertools};
     use k256::SecretKey;
     use rand::thread_rng;
-    use renegade_dealer_api::{DealerRequest, DealerResponse};
+    use renegade_dealer_api::{
+        binary::{BinaryMacKey, BinaryShares, PackedBits},
+        ot::OtResponse,
+        pairwise::{MacMode, PairwiseShare},
+        ring::{ring_mask, RingElement, RingResponse, RingShare},
+        DealerRequest, DealerResponse, Profiles, RequestCounts,
+    };
+    use tokio::sync::mpsc::error::TrySendError;
     use uuid::Uuid;
 
     use super::{
-        create_dealer_sender_receiver, create_response_sender_receiver, Dealer, DealerJob, Scalar,
-        ScalarShare,
+        await_response, create_dealer_sender_receiver, create_response_sender_receiver,
+        sample_mac_key_shares, CurvePoint, Dealer, DealerConfig, DealerError, DealerJob,
+        DealerMessage, DealerSender, Scalar, ScalarShare, MAX_REQUEST_SIZE,
+    };
+    use crate::{
+        metrics::{POOL_HITS, POOL_MISSES},
+        pool::PoolConfig,
     };
 
+    /// The bit-length of the truncation pairs in mock requests
+    const TRUNCATION_BIT_LENGTH: u32 = 96;
+    /// The shift of the truncation pairs in mock requests
+    const TRUNCATION_SHIFT: u32 = 32;
+    /// The bit-length of the edaBits in mock requests
+    const EDABIT_BIT_LENGTH: u32 = 16;
+    /// The degree of the powers tuples in mock requests
+    const POWER_DEGREE: u32 = 5;
+    /// The length of the prefix-product tuples in mock requests
+    const PREFIX_PRODUCT_LENGTH: u32 = 4;
+    /// The length of the inner-product triples in mock requests
+    const INNER_PRODUCT_LENGTH: u32 = 6;
+    /// The rows, inner dimension, and columns of the matrix triples in mock
+    /// requests
+    const MATRIX_DIMS: (u32, u32, u32) = (2, 3, 4);
+    /// The bit-length of values over Z/2^k in mock requests
+    const RING_BIT_LENGTH: u32 = 32;
+    /// The bit-length of the ring MAC key in mock requests
+    const RING_SECURITY_PARAMETER: u32 = 40;
+
     // -----------
     // | Helpers |
     // -----------
@@ -326,12 +1598,29 @@ mod test {
             .with_n_inverse_pairs(n)
             .with_n_random_bits(n)
             .with_n_random_values(n)
+            .with_n_square_pairs(n)
+            .with_n_truncation_pairs(n, TRUNCATION_BIT_LENGTH, TRUNCATION_SHIFT)
+            .with_n_edabits(n, EDABIT_BIT_LENGTH)
+            .with_n_power_tuples(n, POWER_DEGREE)
+            .with_n_prefix_product_tuples(n, PREFIX_PRODUCT_LENGTH)
+            .with_n_inner_product_triples(n, INNER_PRODUCT_LENGTH)
+            .with_n_matrix_triples(n, MATRIX_DIMS.0, MATRIX_DIMS.1, MATRIX_DIMS.2)
+            .with_n_zero_shares(n)
+            .with_n_point_shares(n)
+            .with_n_binary_triples(n)
+            .with_n_ring_random_values(n)
+            .with_n_ring_random_bits(n)
+            .with_n_ring_triples(n)
+            .with_ring_params(RING_BIT_LENGTH, RING_SECURITY_PARAMETER)
+            .with_n_random_ots(n)
+            .with_n_correlated_ots(n)
     }
 
     /// Run a mock dealer
     async fn get_mock_dealer_response(n: u32) -> (DealerResponse, DealerResponse) {
-        let (send, recv) = create_dealer_sender_receiver();
-        Dealer::start(recv);
+        let config = DealerConfig::default();
+        let (send, recv) = create_dealer_sender_receiver(config.queue_capacity);
+        Dealer::start(config, recv);
 
         let (send1, mut recv1) = create_response_sender_receiver();
         let (send2, mut recv2) = create_response_sender_receiver();
@@ -342,11 +1631,25 @@ mod test {
         let job1 = DealerJob::new(rid, PARTY0, req.clone(), send1);
         let job2 = DealerJob::new(rid, PARTY1, req, send2);
 
-        send.send(job1).unwrap();
-        send.send(job2).unwrap();
+        send.send(job1.into()).await.unwrap();
+        send.send(job2.into()).await.unwrap();
 
         // Get two responses
-        (recv1.recv().await.unwrap().unwrap(), recv2.recv().await.unwrap().unwrap())
+        (await_response(&mut recv1).await.unwrap(), await_response(&mut recv2).await.unwrap())
+    }
+
+    /// Submit a request as both parties, returning each party's result
+    async fn submit_pair(
+        send: &DealerSender,
+        rid: Uuid,
+        req: &DealerRequest,
+    ) -> (Result<DealerResponse, DealerError>, Result<DealerResponse, DealerError>) {
+        let (send1, mut recv1) = create_response_sender_receiver();
+        let (send2, mut recv2) = create_response_sender_receiver();
+        send.send(DealerJob::new(rid, PARTY0, req.clone(), send1).into()).await.unwrap();
+        send.send(DealerJob::new(rid, PARTY1, req.clone(), send2).into()).await.unwrap();
+
+        (await_response(&mut recv1).await, await_response(&mut recv2).await)
     }
 
     /// Check that the macs correctly authenticate the given pairs of shares
@@ -368,14 +1671,102 @@ mod test {
         vals
     }
 
-    // ---------
-    // | Tests |
-    // ---------
+    /// Check the OT correlations of a sender and receiver
+    fn check_ots(sender: &OtResponse, receiver: &OtResponse) {
+        let (sender, receiver) = (&sender.sender, &receiver.receiver);
+        let random = receiver.random_choices.iter().zip_eq(receiver.random_messages.iter());
+        for (i, (b, m_b)) in random.enumerate() {
+            let expected = if b { sender.random_m1[i] } else { sender.random_m0[i] };
+            assert_eq!(*m_b, expected);
+        }
 
-    #[tokio::test]
-    async fn test_dealer() {
-        const N: u32 = 10;
-        let (resp1, resp2) = get_mock_dealer_response(N).await;
+        let correlated =
+            receiver.correlated_choices.iter().zip_eq(receiver.correlated_messages.iter());
+        for (i, (b, m_b)) in correlated.enumerate() {
+            let (m0, m1) = sender.correlated_messages(i);
+            assert_eq!(*m_b, if b { m1 } else { m0 });
+        }
+        assert_eq!(sender.correlated_m0.len(), receiver.correlated_messages.len());
+    }
+
+    /// Check the values over Z/2^k of a pair of responses
+    fn check_ring_values(ring1: &RingResponse, ring2: &RingResponse) {
+        assert_eq!(ring1.bit_length, RING_BIT_LENGTH);
+        assert_eq!(ring1.security_parameter, RING_SECURITY_PARAMETER);
+        let share_mask = ring_mask(ring1.share_bit_length());
+
+        // The key shares sum to a key of at most s bits
+        let key = ring1.mac_key_share.wrapping_add(ring2.mac_key_share) & share_mask;
+        assert!(key <= ring_mask(RING_SECURITY_PARAMETER));
+
+        let recover = |shares1: &[RingShare], shares2: &[RingShare]| {
+            shares1
+                .iter()
+                .zip_eq(shares2.iter())
+                .map(|(s1, s2)| open_and_check_ring_mac(ring1, ring2, *s1, *s2))
+                .collect_vec()
+        };
+
+        let bits = recover(&ring1.random_bits, &ring2.random_bits);
+        assert!(bits.into_iter().all(|b| b <= 1));
+        recover(&ring1.random_values, &ring2.random_values);
+
+        // Products hold modulo 2^k, as the secret is the low k bits
+        let a = recover(&ring1.triples.0, &ring2.triples.0);
+        let b = recover(&ring1.triples.1, &ring2.triples.1);
+        let c = recover(&ring1.triples.2, &ring2.triples.2);
+        let value_mask = ring_mask(RING_BIT_LENGTH);
+        for ((a, b), c) in a.into_iter().zip(b).zip(c) {
+            assert_eq!(a.wrapping_mul(b) & value_mask, c);
+        }
+    }
+
+    /// Open a value over Z/2^k and run the SPDZ2k MAC check on it
+    ///
+    /// Each party computes z_i = m_i - α_i * x from its own MAC share and MAC
+    /// key share, the check passes when the z_i sum to zero modulo 2^(k+s).
+    /// Returns the opened value modulo 2^k
+    fn open_and_check_ring_mac(
+        ring1: &RingResponse,
+        ring2: &RingResponse,
+        share1: RingShare,
+        share2: RingShare,
+    ) -> RingElement {
+        let share_mask = ring_mask(ring1.share_bit_length());
+        let mac_check = |opened: RingElement| {
+            let z1 = share1.mac.wrapping_sub(ring1.mac_key_share.wrapping_mul(opened));
+            let z2 = share2.mac.wrapping_sub(ring2.mac_key_share.wrapping_mul(opened));
+            z1.wrapping_add(z2) & share_mask == 0
+        };
+
+        let opened = share1.share.wrapping_add(share2.share) & share_mask;
+        assert!(mac_check(opened));
+        assert!(!mac_check(opened.wrapping_add(1) & share_mask));
+
+        opened & ring_mask(ring1.bit_length)
+    }
+
+    /// Check that the macs correctly authenticate the given pairs of binary
+    /// shares under the given binary key
+    ///
+    /// Return the recovered bits
+    fn recover_and_check_binary_macs(
+        binary_key: BinaryMacKey,
+        shares1: &BinaryShares,
+        shares2: &BinaryShares,
+    ) -> PackedBits {
+        let bits = shares1.bits.xor(&shares2.bits);
+        let macs = shares1.macs.iter().zip(shares2.macs.iter()).map(|(m1, m2)| m1 ^ m2);
+        for (bit, mac) in bits.iter().zip_eq(macs) {
+            assert_eq!(mac, if bit { binary_key } else { 0 });
+        }
+
+        bits
+    }
+
+    /// Check that a pair of responses hold valid, authenticated shares of
+    /// each type of correlated randomness
+    fn check_responses(resp1: &DealerResponse, resp2: &DealerResponse) {
         let mac_key = resp1.mac_key_share + resp2.mac_key_share;
 
         // Check the random bits
@@ -417,5 +1808,618 @@ mod test {
         for (a, b, c) in izip!(a_recovered, b_recovered, c_recovered) {
             assert_eq!(a * b, c);
         }
+
+        // Check the square pairs
+        let (r1, r_sq1) = &resp1.square_pairs;
+        let (r2, r_sq2) = &resp2.square_pairs;
+        let r_recovered = recover_and_check_macs(mac_key, r1, r2);
+        let r_sq_recovered = recover_and_check_macs(mac_key, r_sq1, r_sq2);
+        for (r, r_sq) in r_recovered.iter().zip(r_sq_recovered) {
+            assert_eq!(r * r, r_sq);
+        }
+
+        // Check the truncation pairs, the low bits of r are those dropped by
+        // the shift
+        let (r1, r_shifted1) = &resp1.truncation_pairs;
+        let (r2, r_shifted2) = &resp2.truncation_pairs;
+        let r_recovered = recover_and_check_macs(mac_key, r1, r2);
+        let r_shifted_recovered = recover_and_check_macs(mac_key, r_shifted1, r_shifted2);
+        let scale = Scalar::from(2u64).pow(TRUNCATION_SHIFT as u64);
+        for (r, r_shifted) in r_recovered.into_iter().zip(r_shifted_recovered) {
+            let low_bits = r - r_shifted * scale;
+            assert!(r.to_biguint().bits() <= TRUNCATION_BIT_LENGTH as u64);
+            assert!(low_bits.to_biguint().bits() <= TRUNCATION_SHIFT as u64);
+        }
+
+        // Check the edaBits, the bits must recompose to the value
+        let (r1, bits1) = &resp1.edabits;
+        let (r2, bits2) = &resp2.edabits;
+        let r_recovered = recover_and_check_macs(mac_key, r1, r2);
+        assert_eq!(bits1.len(), r_recovered.len());
+        for (r, bits1, bits2) in izip!(r_recovered, bits1, bits2) {
+            let bits = recover_and_check_macs(mac_key, bits1, bits2);
+            assert_eq!(bits.len(), EDABIT_BIT_LENGTH as usize);
+            assert!(bits.iter().all(|b| *b == Scalar::zero() || *b == Scalar::one()));
+
+            let recomposed: Scalar =
+                bits.iter().enumerate().map(|(i, b)| b * Scalar::from(2u64).pow(i as u64)).sum();
+            assert_eq!(recomposed, r);
+        }
+
+        // Check the powers tuples, each power must be the previous times r
+        assert_eq!(resp1.power_tuples.len(), resp2.power_tuples.len());
+        for (powers1, powers2) in resp1.power_tuples.iter().zip(&resp2.power_tuples) {
+            let powers = recover_and_check_macs(mac_key, powers1, powers2);
+            assert_eq!(powers.len(), POWER_DEGREE as usize);
+            for (i, power) in powers.iter().enumerate() {
+                assert_eq!(*power, powers[0].pow(i as u64 + 1));
+            }
+        }
+
+        // Check the prefix-product tuples
+        assert_eq!(resp1.prefix_product_tuples.len(), resp2.prefix_product_tuples.len());
+        for (tuple1, tuple2) in resp1.prefix_product_tuples.iter().zip(&resp2.prefix_product_tuples)
+        {
+            let r = recover_and_check_macs(mac_key, &tuple1.r, &tuple2.r);
+            let r_inv = recover_and_check_macs(mac_key, &tuple1.r_inv, &tuple2.r_inv);
+            let adjacent = recover_and_check_macs(mac_key, &tuple1.adjacent, &tuple2.adjacent);
+            assert_eq!(r.len(), PREFIX_PRODUCT_LENGTH as usize);
+            assert_eq!(adjacent.len(), r.len() - 1);
+
+            assert!(r.iter().zip(&r_inv).all(|(r, r_inv)| r * r_inv == Scalar::one()));
+            for (i, product) in adjacent.iter().enumerate() {
+                assert_eq!(*product, r[i + 1] * r_inv[i]);
+            }
+        }
+
+        // Check the inner-product triples
+        assert_eq!(resp1.inner_product_triples.len(), resp2.inner_product_triples.len());
+        for (triple1, triple2) in
+            resp1.inner_product_triples.iter().zip(&resp2.inner_product_triples)
+        {
+            let a = recover_and_check_macs(mac_key, &triple1.a, &triple2.a);
+            let b = recover_and_check_macs(mac_key, &triple1.b, &triple2.b);
+            let c = recover_and_check_macs(mac_key, &[triple1.c], &[triple2.c]);
+            assert_eq!(a.len(), INNER_PRODUCT_LENGTH as usize);
+            assert_eq!(b.len(), INNER_PRODUCT_LENGTH as usize);
+            assert_eq!(c[0], a.iter().zip(&b).map(|(a, b)| a * b).sum());
+        }
+
+        // Check the matrix triples
+        let (rows, inner, cols) =
+            (MATRIX_DIMS.0 as usize, MATRIX_DIMS.1 as usize, MATRIX_DIMS.2 as usize);
+        assert_eq!(resp1.matrix_triples.len(), resp2.matrix_triples.len());
+        for (triple1, triple2) in resp1.matrix_triples.iter().zip(&resp2.matrix_triples) {
+            let a = recover_and_check_macs(mac_key, &triple1.a, &triple2.a);
+            let b = recover_and_check_macs(mac_key, &triple1.b, &triple2.b);
+            let c = recover_and_check_macs(mac_key, &triple1.c, &triple2.c);
+            assert_eq!((a.len(), b.len(), c.len()), (rows * inner, inner * cols, rows * cols));
+
+            for (i, j) in (0..rows).cartesian_product(0..cols) {
+                let expected: Scalar = (0..inner).map(|l| a[i * inner + l] * b[l * cols + j]).sum();
+                assert_eq!(c[i * cols + j], expected);
+            }
+        }
+
+        // Check the zero sharings
+        let zeros = recover_and_check_macs(mac_key, &resp1.zero_shares, &resp2.zero_shares);
+        assert!(zeros.into_iter().all(|z| z == Scalar::zero()));
+
+        // Check the random points against the recovered scalars
+        let (points1, points2) = (&resp1.point_shares, &resp2.point_shares);
+        let r1 = points1.iter().map(|p| p.r).collect_vec();
+        let r2 = points2.iter().map(|p| p.r).collect_vec();
+        let r = recover_and_check_macs(mac_key, &r1, &r2);
+        for ((r, p1), p2) in r.into_iter().zip_eq(points1).zip_eq(points2) {
+            assert_eq!(p1.commitment, CurvePoint::generator() * r);
+            assert_eq!(p1.commitment, p2.commitment);
+        }
+
+        // Check the binary triples under the binary MAC key
+        let binary_key = resp1.binary_mac_key_share ^ resp2.binary_mac_key_share;
+        let (triples1, triples2) = (&resp1.binary_triples, &resp2.binary_triples);
+        let a = recover_and_check_binary_macs(binary_key, &triples1.a, &triples2.a);
+        let b = recover_and_check_binary_macs(binary_key, &triples1.b, &triples2.b);
+        let c = recover_and_check_binary_macs(binary_key, &triples1.c, &triples2.c);
+        assert!(a.and(&b) == c);
+
+        check_ring_values(&resp1.ring, &resp2.ring);
+        check_ots(&resp1.ot, &resp2.ot);
+    }
+
+    // ---------
+    // | Tests |
+    // ---------
+
+    #[tokio::test]
+    async fn test_dealer() {
+        const N: u32 = 10;
+        let (resp1, resp2) = get_mock_dealer_response(N).await;
+        check_responses(&resp1, &resp2);
+    }
+
+    /// Tests dealing a request directly, without the pairing service
+    #[test]
+    fn test_deal() {
+        let (resp1, resp2) = Dealer::deal(&mock_dealer_req(10)).unwrap();
+        check_responses(&resp1, &resp2);
+
+        // Invalid counts are rejected rather than dealt
+        let too_large = mock_dealer_req(1).with_n_triples(MAX_REQUEST_SIZE);
+        assert!(matches!(Dealer::deal(&too_large), Err(DealerError::BadRequest(_))));
+        let invalid = mock_dealer_req(1).with_n_truncation_pairs(1, 64 /* bit_length */, 0);
+        assert!(matches!(Dealer::deal(&invalid), Err(DealerError::BadRequest(_))));
+    }
+
+    /// Tests that the jobs held by the dealer stay bounded under a flood of
+    /// requests, and that excess requests are rejected rather than buffered
+    #[tokio::test]
+    async fn test_bounded_under_load() {
+        const N_BURSTS: usize = 100;
+        let config = DealerConfig {
+            queue_capacity: 8,
+            max_open_requests: 16,
+            max_concurrent_jobs: 1,
+            ..Default::default()
+        };
+        let max_held = config.queue_capacity + config.max_open_requests;
+        let (send, recv) = create_dealer_sender_receiver(config.queue_capacity);
+        let dealer = Dealer::start(config.clone(), recv);
+
+        // Flood the dealer with unpaired requests in bursts larger than its queue
+        let req = mock_dealer_req(1);
+        let mut accepted = Vec::new();
+        let mut n_full = 0;
+        for _ in 0..N_BURSTS {
+            for _ in 0..2 * config.queue_capacity {
+                let rid = Uuid::new_v4();
+                let (resp_send, resp_recv) = create_response_sender_receiver();
+                let job = DealerJob::new(rid, PARTY0, req.clone(), resp_send);
+                match send.try_send(job.into()) {
+                    Ok(()) => accepted.push((rid, resp_recv)),
+                    Err(TrySendError::Full(_)) => n_full += 1,
+                    Err(TrySendError::Closed(_)) => panic!("dealer queue closed"),
+                }
+            }
+
+            let n_queued = send.max_capacity() - send.capacity();
+            let n_held = n_queued + dealer.open_requests.lock().unwrap().len();
+            assert!(n_held <= max_held);
+            tokio::task::yield_now().await;
+        }
+
+        // Wait for the dealer to drain its queue
+        while send.capacity() < send.max_capacity() {
+            tokio::task::yield_now().await;
+        }
+        assert!(n_full >= N_BURSTS * config.queue_capacity);
+        assert_eq!(dealer.open_requests.lock().unwrap().len(), config.max_open_requests);
+
+        // Every accepted request beyond the open request limit was rejected
+        let mut open = Vec::new();
+        for (rid, mut recv) in accepted {
+            match recv.try_recv() {
+                Ok(Err(DealerError::Saturated)) => {},
+                Err(_) => open.push((rid, recv)),
+                Ok(_) => panic!("unexpected response for unpaired request"),
+            }
+        }
+        assert_eq!(open.len(), config.max_open_requests);
+
+        // The dealer still serves pairs for its open requests
+        let (rid, mut recv) = open.swap_remove(0);
+        let (resp_send, mut resp_recv) = create_response_sender_receiver();
+        send.send(DealerJob::new(rid, PARTY1, req.clone(), resp_send).into()).await.unwrap();
+        assert!(await_response(&mut recv).await.is_ok());
+        assert!(await_response(&mut resp_recv).await.is_ok());
+
+        // Once their requesters disconnect, the open requests' slots are freed
+        drop(open);
+        let mut fresh = Vec::new();
+        for _ in 0..config.max_open_requests {
+            let (resp_send, resp_recv) = create_response_sender_receiver();
+            let job = DealerJob::new(Uuid::new_v4(), PARTY0, req.clone(), resp_send);
+            send.send(job.into()).await.unwrap();
+            fresh.push(resp_recv);
+        }
+        while send.capacity() < send.max_capacity() {
+            tokio::task::yield_now().await;
+        }
+
+        assert!(fresh.iter_mut().all(|recv| recv.try_recv().is_err()));
+        assert_eq!(dealer.open_requests.lock().unwrap().len(), config.max_open_requests);
+    }
+
+    /// Tests that the dealer's memory stays bounded when pairs arrive faster
+    /// than they are generated
+    ///
+    /// The dealer's memory is held by its jobs, queued, awaiting a
+    /// counterparty, or paired and awaiting generation, and by the values
+    /// generated for them. The test asserts the peak number of jobs held
+    /// under the flood; values are only generated under a permit, so at
+    /// most `max_concurrent_jobs` pairs of responses are buffered at once
+    #[tokio::test]
+    async fn test_bounded_memory_under_load() {
+        const N_PAIRS: usize = 200;
+        let config = DealerConfig {
+            queue_capacity: 8,
+            max_open_requests: 16,
+            max_concurrent_jobs: 1,
+            ..Default::default()
+        };
+        let max_pairs = config.max_concurrent_jobs + config.queue_capacity;
+        let max_held = config.queue_capacity + config.max_open_requests + 2 * max_pairs;
+        let (send, recv) = create_dealer_sender_receiver(config.queue_capacity);
+        let dealer = Dealer::start(config.clone(), recv);
+
+        // Submit pairs without waiting for their responses
+        let req = mock_dealer_req(1);
+        let mut pairs = Vec::new();
+        let mut peak_held = 0;
+        for _ in 0..N_PAIRS {
+            let rid = Uuid::new_v4();
+            let mut recvs = Vec::new();
+            for party_id in [PARTY0, PARTY1] {
+                let (resp_send, resp_recv) = create_response_sender_receiver();
+                let job = DealerJob::new(rid, party_id, req.clone(), resp_send);
+                if send.try_send(job.into()).is_ok() {
+                    recvs.push(resp_recv);
+                }
+            }
+
+            // A request whose counterparty was turned away disconnects
+            if let Ok([recv1, recv2]) = <[_; 2]>::try_from(recvs) {
+                pairs.push((recv1, recv2));
+            }
+
+            let n_queued = send.max_capacity() - send.capacity();
+            let n_open = dealer.open_requests.lock().unwrap().len();
+            let n_paired = 2 * dealer.generating.lock().unwrap().len();
+            peak_held = peak_held.max(n_queued + n_open + n_paired);
+            tokio::task::yield_now().await;
+        }
+        assert!(peak_held <= max_held, "held {peak_held} jobs, bound is {max_held}");
+
+        // Every accepted pair is answered, with its shares or a rejection
+        let mut n_served = 0;
+        for (mut recv1, mut recv2) in pairs {
+            let (res1, res2) = (await_response(&mut recv1).await, await_response(&mut recv2).await);
+            assert_eq!(res1.is_ok(), res2.is_ok());
+            n_served += res1.is_ok() as usize;
+        }
+        assert!(n_served > 0);
+    }
+
+    /// Tests that a request awaiting its counterparty past the TTL expires and
+    /// frees its slot
+    #[tokio::test]
+    async fn test_open_request_expiry() {
+        let config = DealerConfig {
+            max_open_requests: 1,
+            open_request_ttl: Duration::ZERO,
+            ..Default::default()
+        };
+        let (send, recv) = create_dealer_sender_receiver(config.queue_capacity);
+        let dealer = Dealer::start(config, recv);
+
+        let (rid1, rid2) = (Uuid::new_v4(), Uuid::new_v4());
+        let (resp_send1, mut resp_recv1) = create_response_sender_receiver();
+        let (resp_send2, _resp_recv2) = create_response_sender_receiver();
+        send.send(DealerJob::new(rid1, PARTY0, mock_dealer_req(1), resp_send1).into())
+            .await
+            .unwrap();
+        send.send(DealerJob::new(rid2, PARTY0, mock_dealer_req(1), resp_send2).into())
+            .await
+            .unwrap();
+
+        // The second request expires the first and takes its slot
+        let res = await_response(&mut resp_recv1).await;
+        assert!(matches!(res, Err(DealerError::Expired)));
+
+        let open_requests = dealer.open_requests.lock().unwrap();
+        assert!(open_requests.contains_key(&rid2) && !open_requests.contains_key(&rid1));
+    }
+
+    /// Tests that a response channel dropped by the dealer is reported as an
+    /// error rather than a panic
+    #[tokio::test]
+    async fn test_dropped_response_channel() {
+        let (resp_send, mut resp_recv) = create_response_sender_receiver();
+        drop(resp_send);
+
+        let res = await_response(&mut resp_recv).await;
+        assert!(matches!(res, Err(DealerError::Dropped)));
+    }
+
+    /// Tests that cancelling a request awaiting its counterparty frees its slot
+    #[tokio::test]
+    async fn test_cancel_open_request() {
+        let config = DealerConfig::default();
+        let (send, recv) = create_dealer_sender_receiver(config.queue_capacity);
+        let dealer = Dealer::start(config, recv);
+
+        let rid = Uuid::new_v4();
+        let (resp_send, mut resp_recv) = create_response_sender_receiver();
+        send.send(DealerJob::new(rid, PARTY0, mock_dealer_req(1), resp_send).into()).await.unwrap();
+
+        // A cancellation from the wrong party is ignored
+        send.send(DealerMessage::Cancel { request_id: rid, party_id: PARTY1 }).await.unwrap();
+        send.send(DealerMessage::Cancel { request_id: rid, party_id: PARTY0 }).await.unwrap();
+
+        let err = await_response(&mut resp_recv).await.unwrap_err();
+        assert!(matches!(err, DealerError::Cancelled));
+        assert!(dealer.open_requests.lock().unwrap().is_empty());
+    }
+
+    /// Tests that batches in a session share a MAC key, and that a session is
+    /// unusable by other parties or once closed
+    #[tokio::test]
+    async fn test_session_reuses_mac_key() {
+        let config = DealerConfig::default();
+        let (send, recv) = create_dealer_sender_receiver(config.queue_capacity);
+        let dealer = Dealer::start(config, recv);
+
+        // Establish a session, then fetch a second batch in it
+        let session_id = Uuid::new_v4();
+        let req = mock_dealer_req(5).with_session(session_id);
+        let (first1, first2) = submit_pair(&send, session_id, &req).await;
+        let (second1, second2) = submit_pair(&send, Uuid::new_v4(), &req).await;
+        let (first1, first2) = (first1.unwrap(), first2.unwrap());
+        let (second1, second2) = (second1.unwrap(), second2.unwrap());
+
+        assert_eq!(first1.mac_key_share, second1.mac_key_share);
+        assert_eq!(first2.mac_key_share, second2.mac_key_share);
+        assert_eq!(first1.binary_mac_key_share, second1.binary_mac_key_share);
+        assert_eq!(first2.binary_mac_key_share, second2.binary_mac_key_share);
+        assert_eq!(first1.ring.mac_key_share, second1.ring.mac_key_share);
+        assert_eq!(first1.pairwise.mac_key, second1.pairwise.mac_key);
+        check_responses(&second1, &second2);
+
+        // Other parties may not use the session
+        let other_req = mock_dealer_req(5).with_session(session_id);
+        let (res, _) = submit_pair(&send, Uuid::new_v4(), &other_req).await;
+        assert!(matches!(res, Err(DealerError::BadRequest(_))));
+
+        // A closed session may not be used
+        assert!(dealer.close_session(session_id));
+        let (res, _) = submit_pair(&send, Uuid::new_v4(), &req).await;
+        assert!(matches!(res, Err(DealerError::BadRequest(_))));
+    }
+
+    /// Tests that a pair matching a pool shape is served a pre-generated batch
+    /// that is correctly authenticated
+    #[tokio::test]
+    async fn test_pool_serves_matching_shape() {
+        let req = mock_dealer_req(5);
+        let pool = PoolConfig { shapes: vec![req.counts], depth: 1, n_workers: 1 };
+        let config = DealerConfig { pool, ..Default::default() };
+        let (send, recv) = create_dealer_sender_receiver(config.queue_capacity);
+        let dealer = Dealer::start(config, recv);
+
+        // Wait for the pool to fill
+        let pool = dealer.pool.clone().unwrap();
+        while pool.n_ready(&req.counts) == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let hits = POOL_HITS.get();
+        let (resp1, resp2) = submit_pair(&send, Uuid::new_v4(), &req).await;
+        assert_eq!(POOL_HITS.get(), hits + 1);
+        check_responses(&resp1.unwrap(), &resp2.unwrap());
+
+        // A request of another shape is generated on demand without counting a miss
+        let misses = POOL_MISSES.get();
+        let (resp1, resp2) = submit_pair(&send, Uuid::new_v4(), &mock_dealer_req(3)).await;
+        assert_eq!(POOL_MISSES.get(), misses);
+        assert_eq!(resp1.as_ref().unwrap().random_bits.len(), 3);
+        check_responses(&resp1.unwrap(), &resp2.unwrap());
+    }
+
+    /// Tests that pool shapes are validated as requests
+    #[test]
+    fn test_pool_shape_validation() {
+        let mut pool = PoolConfig { shapes: vec![mock_dealer_req(5).counts], ..Default::default() };
+        assert!(pool.validate().is_ok());
+
+        pool.shapes.push(RequestCounts { n_triples: MAX_REQUEST_SIZE + 1, ..Default::default() });
+        assert!(pool.validate().is_err());
+
+        pool.shapes[1] = RequestCounts { n_truncation_pairs: 1, ..Default::default() };
+        assert!(pool.validate().is_err());
+    }
+
+    /// Tests that a request referencing a profile is dealt the profile's
+    /// counts scaled by its multiplier, and that both parties must reference
+    /// the same known profile
+    #[tokio::test]
+    async fn test_profiles() {
+        let counts = mock_dealer_req(2).counts;
+        let profiles = Profiles::from([("settle".to_string(), counts)]);
+        let config = DealerConfig { profiles, ..Default::default() };
+        let (send, recv) = create_dealer_sender_receiver(config.queue_capacity);
+        Dealer::start(config, recv);
+
+        let keys = mock_dealer_req(0);
+        let req = keys.clone().with_scaled_profile("settle", 3 /* multiplier */);
+        let (resp1, resp2) = submit_pair(&send, Uuid::new_v4(), &req).await;
+        let (resp1, resp2) = (resp1.unwrap(), resp2.unwrap());
+        assert_eq!(resp1.random_bits.len(), 6);
+        assert_eq!(resp1.beaver_triples.0.len(), 6);
+        check_responses(&resp1, &resp2);
+
+        // Both parties must reference the same profile
+        let rid = Uuid::new_v4();
+        let (send1, mut recv1) = create_response_sender_receiver();
+        let (send2, _recv2) = create_response_sender_receiver();
+        let other_req = keys.clone().with_profile("settle");
+        send.send(DealerJob::new(rid, PARTY0, req, send1).into()).await.unwrap();
+        send.send(DealerJob::new(rid, PARTY1, other_req, send2).into()).await.unwrap();
+        let err = await_response(&mut recv1).await.unwrap_err();
+        assert!(matches!(err, DealerError::BadRequest("Mismatched profiles")));
+
+        // Unknown profiles and oversized multipliers are rejected on arrival
+        for req in
+            [keys.clone().with_profile("unknown"), keys.with_scaled_profile("settle", u32::MAX)]
+        {
+            let (resp_send, mut resp_recv) = create_response_sender_receiver();
+            send.send(DealerJob::new(Uuid::new_v4(), PARTY0, req, resp_send).into()).await.unwrap();
+            assert!(matches!(
+                await_response(&mut resp_recv).await,
+                Err(DealerError::BadRequest(_))
+            ));
+        }
+    }
+
+    /// Tests that each party is dealt its own number of input masks, along with
+    /// shares of its counterparty's masks
+    #[test]
+    fn test_asymmetric_input_masks() {
+        let req = mock_dealer_req(0)
+            .with_n_input_masks(1)
+            .with_n_first_party_input_masks(4)
+            .with_n_second_party_input_masks(2);
+        let (resp1, resp2) = Dealer::deal(&req).unwrap();
+
+        let (masks1, mask1_shares1, mask2_shares1) = &resp1.input_masks;
+        let (masks2, mask2_shares2, mask1_shares2) = &resp2.input_masks;
+        assert_eq!((masks1.len(), mask1_shares1.len(), mask2_shares1.len()), (5, 5, 3));
+        assert_eq!((masks2.len(), mask2_shares2.len(), mask1_shares2.len()), (3, 3, 5));
+        check_responses(&resp1, &resp2);
+    }
+
+    /// Tests that values with pairwise MACs reconstruct correctly and that
+    /// each party's checks accept the counterparty's openings
+    #[test]
+    fn test_pairwise_macs() {
+        let req = mock_dealer_req(5).with_mac_mode(MacMode::Pairwise);
+        let (resp1, resp2) = Dealer::deal(&req).unwrap();
+        assert!(resp1.random_values.is_empty() && resp1.beaver_triples.0.is_empty());
+        check_responses(&resp1, &resp2);
+
+        let (pairwise1, pairwise2) = (&resp1.pairwise, &resp2.pairwise);
+        let recover = |shares1: &[PairwiseShare], shares2: &[PairwiseShare]| {
+            assert_eq!(shares1.len(), 5);
+            shares1
+                .iter()
+                .zip_eq(shares2.iter())
+                .map(|(s1, s2)| {
+                    // Each party checks the other's opening under its own keys
+                    assert!(s2.check_opening(pairwise2.mac_key, s1.share, s1.mac));
+                    assert!(s1.check_opening(pairwise1.mac_key, s2.share, s2.mac));
+                    assert!(!s2.check_opening(pairwise2.mac_key, s1.share + Scalar::one(), s1.mac));
+                    s1.share + s2.share
+                })
+                .collect_vec()
+        };
+
+        let bits = recover(&pairwise1.random_bits, &pairwise2.random_bits);
+        assert!(bits.into_iter().all(|b| b == Scalar::zero() || b == Scalar::one()));
+        recover(&pairwise1.random_values, &pairwise2.random_values);
+
+        let (a1, b1, c1) = &pairwise1.beaver_triples;
+        let (a2, b2, c2) = &pairwise2.beaver_triples;
+        let (a, b, c) = (recover(a1, a2), recover(b1, b2), recover(c1, c2));
+        for (a, b, c) in izip!(a, b, c) {
+            assert_eq!(a * b, c);
+        }
+    }
+
+    /// Tests that compressing a pair of responses and expanding the seed
+    /// recovers the same values as the uncompressed responses
+    #[test]
+    fn test_compressed_matches_uncompressed() {
+        let (resp1, resp2) = Dealer::deal(&mock_dealer_req(5)).unwrap();
+        let (mut compressed1, mut compressed2) = (resp1.clone(), resp2.clone());
+        Dealer::compress_shares(&mut compressed1, &mut compressed2);
+        assert!(compressed1.random_values.is_empty() && compressed1.compressed.is_some());
+
+        let size = |resp: &DealerResponse| serde_json::to_vec(resp).unwrap().len();
+        assert!(size(&compressed1) < size(&resp1));
+
+        compressed1.expand_compressed();
+        let mac_key = resp1.mac_key_share + resp2.mac_key_share;
+        let recover = |r1: &DealerResponse, r2: &DealerResponse| {
+            let (a1, b1, c1) = &r1.beaver_triples;
+            let (a2, b2, c2) = &r2.beaver_triples;
+            [
+                (&r1.random_bits, &r2.random_bits),
+                (&r1.random_values, &r2.random_values),
+                (a1, a2),
+                (b1, b2),
+                (c1, c2),
+            ]
+            .map(|(s1, s2)| recover_and_check_macs(mac_key, s1, s2))
+        };
+        assert_eq!(recover(&compressed1, &compressed2), recover(&resp1, &resp2));
+
+        // The dealer compresses on request
+        let (mut resp1, resp2) = Dealer::deal(&mock_dealer_req(5).with_compression()).unwrap();
+        assert!(resp1.compressed.is_some());
+        resp1.expand_compressed();
+        check_responses(&resp1, &resp2);
+    }
+
+    /// Tests that the OT sender role follows the requested party
+    #[test]
+    fn test_ot_sender_role() {
+        let req = mock_dealer_req(0).with_n_random_ots(5).with_n_correlated_ots(3);
+        let (resp1, resp2) = Dealer::deal(&req.with_ot_sender(PARTY1)).unwrap();
+
+        assert!(resp1.ot.sender.random_m0.is_empty() && resp1.ot.sender.correlated_m0.is_empty());
+        assert!(resp2.ot.receiver.random_messages.is_empty());
+        assert_eq!((resp2.ot.n_random(), resp2.ot.n_correlated()), (5, 3));
+        check_ots(&resp2.ot, &resp1.ot);
+    }
+
+    /// Tests that truncation pairs are bounded by their bit-length, including
+    /// bit-lengths that are not a multiple of the limb size
+    #[test]
+    fn test_truncation_pair_bounds() {
+        let req = mock_dealer_req(0).with_n_truncation_pairs(50, 130 /* bit_length */, 70);
+        let (resp1, resp2) = Dealer::deal(&req).unwrap();
+
+        let mac_key = resp1.mac_key_share + resp2.mac_key_share;
+        let (r1, r_shifted1) = &resp1.truncation_pairs;
+        let (r2, r_shifted2) = &resp2.truncation_pairs;
+        let r = recover_and_check_macs(mac_key, r1, r2);
+        let r_shifted = recover_and_check_macs(mac_key, r_shifted1, r_shifted2);
+
+        let scale = Scalar::from(2u64).pow(70);
+        for (r, r_shifted) in r.into_iter().zip(r_shifted) {
+            assert!(r.to_biguint().bits() <= 130);
+            assert!(r_shifted.to_biguint().bits() <= 60);
+            assert!((r - r_shifted * scale).to_biguint().bits() <= 70);
+        }
+    }
+
+    /// Tests that sharings for more than two parties recombine to the shared
+    /// values under the MAC key
+    #[test]
+    fn test_n_party_shares() {
+        let mut rng = thread_rng();
+        let mac_key = Scalar::random(&mut rng);
+        let values = (0..5).map(|_| Scalar::random(&mut rng)).collect_vec();
+
+        let shares = Dealer::gen_n_party_authenticated_shares::<4>(mac_key, &values);
+        for (i, value) in values.iter().enumerate() {
+            let share: Scalar = shares.iter().map(|party| party[i].share()).sum();
+            let mac: Scalar = shares.iter().map(|party| party[i].mac()).sum();
+            assert_eq!(share, *value);
+            assert_eq!(mac, value * mac_key);
+        }
+    }
+
+    /// Tests that generation reports progress and stops when cancelled
+    #[test]
+    fn test_generation_cancelled() {
+        let req = mock_dealer_req(10);
+        let mut progress = Vec::new();
+        let res = Dealer::generate(&req.counts, sample_mac_key_shares(), |generated| {
+            progress.push(generated);
+            progress.len() < 2
+        });
+
+        assert!(res.is_none());
+        assert_eq!(progress, vec![10, 20]);
     }
 }
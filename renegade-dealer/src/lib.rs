@@ -0,0 +1,23 @@
+//! The Renegade Dealer
+//!
+//! A trusted dealer for the SPDZ offline phase. The dealer may be used
+//! directly to generate correlated randomness for a pair of parties via
+//! [`Dealer::deal`], or run as a service that pairs the requests of two
+//! parties and responds to each with its shares via [`Dealer::start`]
+//!
+//! The HTTP server in this crate is a thin wrapper around the service
+
+#![deny(missing_docs)]
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(unsafe_code)]
+#![deny(clippy::needless_pass_by_value)]
+#![deny(clippy::needless_pass_by_ref_mut)]
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+#![feature(inherent_associated_types)]
+
+pub mod dealer;
+pub mod metrics;
+pub mod pool;
+
+pub use dealer::{Dealer, DealerConfig, DealerError, DealerEvent, DealerJob, DealerMessage};
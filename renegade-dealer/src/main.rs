@@ -5,7 +5,8 @@
 //! dealer as opposed to the Lowgear implementation
 //!
 //! The dealer awaits two parties to connect (authenticated with a signature)
-//! and then deals sets of correlated randomness shares to each party
+//! and then deals sets of correlated randomness shares to each party. The
+//! server delegates pairing and generation to the dealer library
 
 #![deny(missing_docs)]
 #![deny(clippy::missing_docs_in_private_items)]
@@ -16,27 +17,51 @@
 #![feature(generic_const_exprs)]
 #![feature(inherent_associated_types)]
 
-mod dealer;
+mod async_jobs;
+mod ws;
+
+use std::{
+    num::NonZeroUsize,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use ark_mpc::PARTY0;
 use ark_mpc::{network::PartyId, PARTY1};
+use async_jobs::{AsyncJobStore, FetchError};
 use base64::prelude::*;
-use clap::Parser;
-use dealer::{
-    create_dealer_sender_receiver, create_response_sender_receiver, Dealer, DealerJob, DealerSender,
-};
+use clap::{error::ErrorKind, CommandFactory, Parser, ValueEnum};
 use k256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use renegade_dealer::{
+    dealer::{
+        await_response, create_dealer_sender_receiver, create_response_sender_receiver,
+        DealerMessage, DealerSender, ResponseReceiver, MAX_REQUEST_SIZE,
+    },
+    metrics::{
+        encode_metrics, record_rejection, JOB_QUEUE_LENGTH, REQUESTS_RECEIVED, REQUEST_LATENCY,
+    },
+    pool::PoolConfig,
+    Dealer, DealerConfig, DealerError, DealerJob,
+};
 use renegade_dealer_api::{
-    DealerRequest, DealerResponse, ErrorResponse, RequestId, PARTY_ID_HEADER, SIGNATURE_HEADER,
+    result_fetch_payload, session_close_payload, AsyncSubmitResponse, DealerRequest,
+    DealerResponse, ErrorResponse, ListProfilesResponse, Profiles, RequestCounts, RequestId,
+    RequestStatus, SessionId, PARTY_ID_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER,
 };
+use tokio::sync::mpsc::error::TrySendError;
+use tracing::{debug, info, instrument, warn};
+use tracing_subscriber::EnvFilter;
 use uuid::Uuid;
-use warp::Filter;
+use warp::{http::StatusCode, reply::Response, Filter, Reply};
+use ws::handle_websocket;
 
-/// The maximum number of values that may be requested at once by a pair
-const MAX_REQUEST_SIZE: u32 = 1_500_000;
+/// The number of seconds a client should wait before retrying a request
+/// rejected because the dealer is saturated
+const RETRY_AFTER_SECS: u64 = 1;
+/// The maximum age of a signed result fetch, in milliseconds
+const MAX_SIGNATURE_AGE_MS: u64 = 30_000;
 
 /// An error type indicating a bad request
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 struct BadRequestError(&'static str);
 impl warp::reject::Reject for BadRequestError {}
 
@@ -45,6 +70,37 @@ impl warp::reject::Reject for BadRequestError {}
 struct UnauthorizedError(&'static str);
 impl warp::reject::Reject for UnauthorizedError {}
 
+/// An error type indicating the requested resource does not exist
+#[derive(Debug)]
+struct NotFoundError(&'static str);
+impl warp::reject::Reject for NotFoundError {}
+
+/// An error type indicating the request conflicts with the dealer's state
+#[derive(Debug)]
+struct ConflictError(&'static str);
+impl warp::reject::Reject for ConflictError {}
+
+/// An error type indicating the dealer failed to handle the request
+#[derive(Debug)]
+struct InternalError(&'static str);
+impl warp::reject::Reject for InternalError {}
+
+/// An error type indicating the dealer is at capacity
+#[derive(Debug)]
+struct DealerSaturatedError;
+impl warp::reject::Reject for DealerSaturatedError {}
+
+/// Convert an error from the dealer into a rejection
+fn dealer_rejection(err: &DealerError) -> warp::Rejection {
+    match err {
+        DealerError::BadRequest(msg) => warp::reject::custom(BadRequestError(msg)),
+        DealerError::Saturated => warp::reject::custom(DealerSaturatedError),
+        DealerError::Cancelled => warp::reject::custom(ConflictError("Request cancelled")),
+        DealerError::Expired => warp::reject::custom(ConflictError("Request expired")),
+        DealerError::Dropped => warp::reject::custom(InternalError("Dealer dropped the request")),
+    }
+}
+
 /// Renegade Dealer server configuration
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -52,43 +108,267 @@ struct Cli {
     /// Port to listen on
     #[clap(short, long, default_value_t = 3000)]
     port: u16,
+    /// The format in which to emit logs
+    #[clap(long, value_enum, default_value_t = LogFormat::Pretty)]
+    log_format: LogFormat,
+    /// The maximum number of requests buffered in the dealer's queue
+    #[clap(long, default_value_t = 1024)]
+    queue_capacity: usize,
+    /// The maximum number of requests awaiting their counterparty
+    #[clap(long, default_value_t = 1024)]
+    max_open_requests: usize,
+    /// The number of seconds for which a request awaits its counterparty
+    /// before it expires
+    #[clap(long, default_value_t = 300)]
+    open_request_ttl_secs: u64,
+    /// The maximum number of request pairs for which the dealer generates
+    /// values concurrently
+    #[clap(long, default_value_t = 4)]
+    max_concurrent_jobs: usize,
+    /// The number of seconds for which the result of an asynchronously
+    /// submitted request is kept
+    #[clap(long, default_value_t = 300)]
+    async_result_ttl_secs: u64,
+    /// The number of seconds for which a session's MAC key is kept after the
+    /// session is established
+    #[clap(long, default_value_t = 3600)]
+    session_ttl_secs: u64,
+    /// A request shape for which the dealer pre-generates batches, given as a
+    /// JSON object of counts, e.g. `{"n_triples": 1000}`
+    ///
+    /// May be given multiple times
+    #[clap(long = "pool-shape", value_parser = parse_counts)]
+    pool_shapes: Vec<RequestCounts>,
+    /// The number of batches pre-generated for each pool shape
+    #[clap(long, default_value_t = 0)]
+    pool_depth: usize,
+    /// The number of background workers refilling the pool
+    #[clap(long, default_value_t = 1)]
+    pool_workers: usize,
+    /// The path of a JSON file mapping profile names to the counts they
+    /// request, e.g. `{"settle": {"n_triples": 1000, "n_random_bits": 64}}`
+    #[clap(long, value_parser = parse_profiles)]
+    profiles: Option<Profiles>,
+    /// The maximum number of values of each type sent in a single websocket
+    /// message
+    #[clap(long, default_value_t = NonZeroUsize::new(10_000).unwrap())]
+    ws_chunk_size: NonZeroUsize,
+}
+
+/// Parse the counts of a request shape from JSON
+fn parse_counts(s: &str) -> Result<RequestCounts, String> {
+    serde_json::from_str(s).map_err(|e| format!("invalid request shape: {e}"))
+}
+
+/// Load the request profiles from a JSON file
+fn parse_profiles(path: &str) -> Result<Profiles, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("cannot read {path}: {e}"))?;
+    serde_json::from_str(&contents).map_err(|e| format!("invalid profiles: {e}"))
+}
+
+/// The output format of the server's logs
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// Human readable, multi-line logs
+    Pretty,
+    /// Newline delimited JSON logs
+    Json,
+}
+
+/// Setup the global tracing subscriber
+///
+/// The log level is configured via the `RUST_LOG` environment variable and
+/// defaults to `info`
+fn setup_logging(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match format {
+        LogFormat::Pretty => builder.pretty().init(),
+        LogFormat::Json => builder.json().with_current_span(true).with_span_list(true).init(),
+    }
 }
 
 /// Main entry point for the Renegade Dealer
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
+    setup_logging(cli.log_format);
 
     // Start a dealer
-    let (dealer_send, dealer_recv) = create_dealer_sender_receiver();
-    Dealer::start(dealer_recv);
+    let config = DealerConfig {
+        queue_capacity: cli.queue_capacity,
+        max_open_requests: cli.max_open_requests,
+        open_request_ttl: Duration::from_secs(cli.open_request_ttl_secs),
+        max_concurrent_jobs: cli.max_concurrent_jobs,
+        session_ttl: Duration::from_secs(cli.session_ttl_secs),
+        pool: PoolConfig {
+            shapes: cli.pool_shapes,
+            depth: cli.pool_depth,
+            n_workers: cli.pool_workers,
+        },
+        profiles: cli.profiles.unwrap_or_default(),
+    };
+    if let Err(msg) = config.pool.validate() {
+        Cli::command()
+            .error(ErrorKind::ValueValidation, format!("invalid pool shape: {msg}"))
+            .exit();
+    }
+
+    let (dealer_send, dealer_recv) = create_dealer_sender_receiver(config.queue_capacity);
+    let dealer = Dealer::start(config, dealer_recv);
+
+    let async_result_ttl = Duration::from_secs(cli.async_result_ttl_secs);
+    let async_jobs = AsyncJobStore::new(async_result_ttl, dealer_send.clone());
+    let routes = routes(dealer_send, dealer, async_jobs, cli.ws_chunk_size);
+
+    info!("starting dealer on port {}", cli.port);
+    warp::serve(routes).run(([0, 0, 0, 0], cli.port)).await
+}
 
+/// Build the server's routes over the given dealer and async job store
+fn routes(
+    dealer_send: DealerSender,
+    dealer: Dealer,
+    async_jobs: AsyncJobStore,
+    ws_chunk_size: NonZeroUsize,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
     // POST /v0/offline-phase/:request_id
+    let dealer_send_clone = dealer_send.clone();
     let offline_phase = warp::post()
         .and(warp::path("v0"))
         .and(warp::path("offline-phase"))
         .and(warp::path::param::<RequestId>())
+        .and(warp::path::end())
         .and(warp::header::header::<PartyId>(PARTY_ID_HEADER))
         .and(warp::header::header::<String>(SIGNATURE_HEADER))
         .and(warp::body::json::<DealerRequest>())
         .and_then(move |request_id, party_id, sig, body| {
-            let dealer_send = dealer_send.clone();
+            let dealer_send = dealer_send_clone.clone();
             async move {
                 match handle_req(request_id, party_id, sig, body, dealer_send).await {
                     Ok(resp) => Ok(warp::reply::json(&resp)),
                     Err(rej) => Err(rej),
                 }
             }
-        })
-        .recover(handle_rejection);
+        });
+
+    // GET /v0/offline-phase/:request_id/ws
+    let dealer_send_clone = dealer_send.clone();
+    let offline_phase_ws = warp::get()
+        .and(warp::path("v0"))
+        .and(warp::path("offline-phase"))
+        .and(warp::path::param::<RequestId>())
+        .and(warp::path("ws"))
+        .and(warp::path::end())
+        .and(warp::ws())
+        .map(move |request_id, ws: warp::ws::Ws| {
+            let dealer_send = dealer_send_clone.clone();
+            ws.on_upgrade(move |socket| {
+                handle_websocket(socket, request_id, dealer_send, ws_chunk_size)
+            })
+        });
+
+    // POST /v0/offline-phase/:request_id/async
+    let async_jobs_clone = async_jobs.clone();
+    let offline_phase_async = warp::post()
+        .and(warp::path("v0"))
+        .and(warp::path("offline-phase"))
+        .and(warp::path::param::<RequestId>())
+        .and(warp::path("async"))
+        .and(warp::path::end())
+        .and(warp::header::header::<PartyId>(PARTY_ID_HEADER))
+        .and(warp::header::header::<String>(SIGNATURE_HEADER))
+        .and(warp::body::json::<DealerRequest>())
+        .and_then(move |request_id, party_id, sig, body| {
+            let dealer_send = dealer_send.clone();
+            let async_jobs = async_jobs_clone.clone();
+            async move {
+                handle_async_req(request_id, party_id, sig, body, dealer_send, async_jobs).await?;
+                let resp = warp::reply::json(&AsyncSubmitResponse { request_id });
+                Ok::<_, warp::Rejection>(warp::reply::with_status(resp, StatusCode::ACCEPTED))
+            }
+        });
+
+    // GET /v0/offline-phase/:request_id/status
+    let async_jobs_clone = async_jobs.clone();
+    let offline_phase_status = warp::get()
+        .and(warp::path("v0"))
+        .and(warp::path("offline-phase"))
+        .and(warp::path::param::<RequestId>())
+        .and(warp::path("status"))
+        .and(warp::path::end())
+        .and(warp::header::header::<PartyId>(PARTY_ID_HEADER))
+        .and_then(move |request_id, party_id| {
+            let async_jobs = async_jobs_clone.clone();
+            async move {
+                let status = handle_status_req(request_id, party_id, &async_jobs)?;
+                Ok::<_, warp::Rejection>(warp::reply::json(&status))
+            }
+        });
+
+    // GET /v0/offline-phase/:request_id/result
+    let offline_phase_result = warp::get()
+        .and(warp::path("v0"))
+        .and(warp::path("offline-phase"))
+        .and(warp::path::param::<RequestId>())
+        .and(warp::path("result"))
+        .and(warp::path::end())
+        .and(warp::header::header::<PartyId>(PARTY_ID_HEADER))
+        .and(warp::header::header::<String>(SIGNATURE_HEADER))
+        .and(warp::header::header::<u64>(TIMESTAMP_HEADER))
+        .and_then(move |request_id, party_id, sig: String, timestamp| {
+            let async_jobs = async_jobs.clone();
+            async move {
+                let resp = handle_result_req(request_id, party_id, &sig, timestamp, &async_jobs)?;
+                Ok::<_, warp::Rejection>(warp::reply::json(&resp))
+            }
+        });
+
+    // GET /v0/profiles
+    let profiles = ListProfilesResponse { profiles: dealer.profiles().clone() };
+    let list_profiles = warp::get()
+        .and(warp::path("v0"))
+        .and(warp::path("profiles"))
+        .and(warp::path::end())
+        .map(move || warp::reply::json(&profiles));
+
+    // DELETE /v0/session/:session_id
+    let close_session = warp::delete()
+        .and(warp::path("v0"))
+        .and(warp::path("session"))
+        .and(warp::path::param::<SessionId>())
+        .and(warp::path::end())
+        .and(warp::header::header::<PartyId>(PARTY_ID_HEADER))
+        .and(warp::header::header::<String>(SIGNATURE_HEADER))
+        .and(warp::header::header::<u64>(TIMESTAMP_HEADER))
+        .and_then(move |session_id, party_id, sig: String, timestamp| {
+            let dealer = dealer.clone();
+            async move {
+                handle_close_session(session_id, party_id, &sig, timestamp, &dealer)?;
+                Ok::<_, warp::Rejection>(StatusCode::NO_CONTENT)
+            }
+        });
 
     // GET /ping
     let ping = warp::get()
         .and(warp::path("ping"))
-        .map(|| warp::reply::with_status("PONG", warp::http::StatusCode::OK));
+        .map(|| warp::reply::with_status("PONG", StatusCode::OK));
 
-    let routes = offline_phase.or(ping);
-    warp::serve(routes).run(([0, 0, 0, 0], cli.port)).await
+    // GET /metrics
+    let metrics = warp::get().and(warp::path("metrics")).map(|| {
+        warp::reply::with_header(encode_metrics(), "content-type", "text/plain; version=0.0.4")
+    });
+
+    offline_phase
+        .or(offline_phase_async)
+        .or(offline_phase_ws)
+        .or(offline_phase_status)
+        .or(offline_phase_result)
+        .or(list_profiles)
+        .or(close_session)
+        .or(ping)
+        .or(metrics)
+        .recover(handle_rejection)
 }
 
 /// Validates the incoming request headers and body.
@@ -103,25 +383,87 @@ fn validate_request(
         return Err(warp::reject::custom(BadRequestError("Request size too large")));
     }
 
+    if let Err(msg) = body.counts.validate() {
+        return Err(warp::reject::custom(BadRequestError(msg)));
+    }
+
     // Party ID validation
     if !(party_id == PARTY0 || party_id == PARTY1) {
         return Err(warp::reject::custom(BadRequestError("Invalid party ID")));
     }
 
     // Verify the signature
-    let key: VerifyingKey =
-        if party_id == PARTY0 { body.first_party_key } else { body.second_party_key }.into();
-    let decoded = BASE64_STANDARD.decode(signature.as_bytes()).unwrap();
-    let sig = Signature::from_slice(&decoded).unwrap();
-
     let body_bytes = serde_json::to_vec(&body).unwrap();
     let payload = [request_id.to_bytes_le().as_ref(), &body_bytes].concat();
-    key.verify(&payload, &sig).map_err(|_| UnauthorizedError("Invalid signature"))?;
+    verify_signature(&party_key(party_id, body), &payload, signature)
+}
 
-    Ok(())
+/// Get the key of the given party in a request
+fn party_key(party_id: PartyId, body: &DealerRequest) -> VerifyingKey {
+    if party_id == PARTY0 { body.first_party_key } else { body.second_party_key }.into()
+}
+
+/// Verify a base64 encoded signature over the given payload
+fn verify_signature(
+    key: &VerifyingKey,
+    payload: &[u8],
+    signature: &str,
+) -> Result<(), warp::Rejection> {
+    let invalid_sig = || warp::reject::custom(UnauthorizedError("Invalid signature"));
+    let decoded = BASE64_STANDARD.decode(signature.as_bytes()).map_err(|_| invalid_sig())?;
+    let sig = Signature::from_slice(&decoded).map_err(|_| invalid_sig())?;
+
+    key.verify(payload, &sig).map_err(|_| invalid_sig())
+}
+
+/// Validate a request and enqueue it with the dealer
+///
+/// Returns the channel on which the dealer will send the job's events
+fn enqueue_request(
+    request_id: RequestId,
+    party_id: PartyId,
+    signature: &str,
+    body: DealerRequest,
+    dealer_queue: &DealerSender,
+) -> Result<ResponseReceiver, warp::Rejection> {
+    REQUESTS_RECEIVED.inc();
+    if let Err(rej) = validate_request(request_id, party_id, signature, &body) {
+        warn!("request failed validation: {rej:?}");
+        return Err(rej);
+    }
+
+    // Enqueue the job without waiting, a full queue means the dealer is saturated
+    let (send, recv) = create_response_sender_receiver();
+    match dealer_queue.try_send(DealerJob::new(request_id, party_id, body, send).into()) {
+        Ok(()) => JOB_QUEUE_LENGTH.inc(),
+        Err(TrySendError::Full(_)) => {
+            warn!("dealer queue full, rejecting request");
+            return Err(warp::reject::custom(DealerSaturatedError));
+        },
+        Err(TrySendError::Closed(_)) => {
+            warn!("dealer queue closed, rejecting request");
+            return Err(warp::reject::custom(InternalError("Dealer unavailable")));
+        },
+    }
+
+    debug!("queued request for dealer");
+    Ok(recv)
+}
+
+/// Cancel a request with the dealer
+async fn cancel_request(request_id: RequestId, party_id: PartyId, dealer_queue: &DealerSender) {
+    // Cancellations wait for queue capacity rather than being dropped
+    let cancel = DealerMessage::Cancel { request_id, party_id };
+    if dealer_queue.send(cancel).await.is_ok() {
+        JOB_QUEUE_LENGTH.inc();
+    }
 }
 
 /// Handle an incoming client request
+///
+/// Only the request ID and party ID are recorded on the span, the request
+/// body and signature are skipped
+#[instrument(name = "dealer_request", skip_all, fields(request_id = %request_id, party_id = party_id))]
 async fn handle_req(
     request_id: RequestId,
     party_id: PartyId,
@@ -129,22 +471,385 @@ async fn handle_req(
     body: DealerRequest,
     dealer_queue: DealerSender,
 ) -> Result<DealerResponse, warp::Rejection> {
-    validate_request(request_id, party_id, &signature, &body)?;
-    let (send, mut recv) = create_response_sender_receiver();
-    dealer_queue.send(DealerJob::new(request_id, party_id, body, send)).unwrap();
+    let _timer = REQUEST_LATENCY.start_timer();
+    let mut recv = enqueue_request(request_id, party_id, &signature, body, &dealer_queue)?;
+
+    match await_response(&mut recv).await {
+        Ok(resp) => {
+            info!("sending response to client");
+            Ok(resp)
+        },
+        Err(err) => {
+            warn!("dealer rejected request: {err}");
+            Err(dealer_rejection(&err))
+        },
+    }
+}
+
+/// Handle an incoming client request submitted asynchronously
+///
+/// The dealer's response is held in the async job store until fetched
+#[instrument(name = "dealer_request", skip_all, fields(request_id = %request_id, party_id = party_id))]
+async fn handle_async_req(
+    request_id: RequestId,
+    party_id: PartyId,
+    signature: String,
+    body: DealerRequest,
+    dealer_queue: DealerSender,
+    async_jobs: AsyncJobStore,
+) -> Result<(), warp::Rejection> {
+    // Reserve the job's slot first, so that concurrent duplicates are rejected
+    // before either reaches the dealer
+    let key = party_key(party_id, &body);
+    if !async_jobs.reserve(request_id, party_id, key) {
+        return Err(warp::reject::custom(BadRequestError("Duplicate request")));
+    }
+
+    match enqueue_request(request_id, party_id, &signature, body, &dealer_queue) {
+        Ok(recv) => async_jobs.track(request_id, party_id, recv),
+        Err(rej) => {
+            async_jobs.release(request_id, party_id);
+            return Err(rej);
+        },
+    }
+
+    info!("accepted async request");
+    Ok(())
+}
+
+/// Handle a request for the status of an asynchronously submitted request
+fn handle_status_req(
+    request_id: RequestId,
+    party_id: PartyId,
+    async_jobs: &AsyncJobStore,
+) -> Result<RequestStatus, warp::Rejection> {
+    async_jobs
+        .status(request_id, party_id)
+        .ok_or_else(|| warp::reject::custom(NotFoundError("Request not found")))
+}
+
+/// Handle a request for the result of an asynchronously submitted request
+///
+/// The request must be signed by the party's key over a recent timestamp,
+/// the result is deleted once returned
+#[instrument(name = "result_request", skip_all, fields(request_id = %request_id, party_id = party_id))]
+fn handle_result_req(
+    request_id: RequestId,
+    party_id: PartyId,
+    signature: &str,
+    timestamp: u64,
+    async_jobs: &AsyncJobStore,
+) -> Result<DealerResponse, warp::Rejection> {
+    let not_found = || warp::reject::custom(NotFoundError("Result not found"));
+    let key = async_jobs.party_key(request_id, party_id).ok_or_else(not_found)?;
+
+    // Check that the signature is fresh, then verify it
+    let payload = result_fetch_payload(request_id, party_id, timestamp);
+    if let Err(rej) = verify_fresh_signature(&key, &payload, signature, timestamp) {
+        warn!("result fetch failed authentication");
+        return Err(rej);
+    }
+
+    match async_jobs.take_result(request_id, party_id) {
+        Ok(resp) => {
+            info!("sending async result to client");
+            Ok(resp)
+        },
+        Err(FetchError::NotFound) => Err(not_found()),
+        Err(FetchError::NotReady) => Err(warp::reject::custom(ConflictError("Result not ready"))),
+    }
+}
+
+/// Handle a request to close a session
+///
+/// The request must be signed by the key of either party to the session over
+/// a recent timestamp
+#[instrument(name = "close_session", skip_all, fields(session_id = %session_id, party_id = party_id))]
+fn handle_close_session(
+    session_id: SessionId,
+    party_id: PartyId,
+    signature: &str,
+    timestamp: u64,
+    dealer: &Dealer,
+) -> Result<(), warp::Rejection> {
+    let not_found = || warp::reject::custom(NotFoundError("Session not found"));
+    let key = dealer.session_party_key(session_id, party_id).ok_or_else(not_found)?;
+
+    let payload = session_close_payload(session_id, party_id, timestamp);
+    if let Err(rej) = verify_fresh_signature(&key.into(), &payload, signature, timestamp) {
+        warn!("session close failed authentication");
+        return Err(rej);
+    }
+
+    if !dealer.close_session(session_id) {
+        return Err(not_found());
+    }
+
+    info!("closed session");
+    Ok(())
+}
+
+/// Verify a signature over a payload that includes a timestamp, checking
+/// that the timestamp is recent
+fn verify_fresh_signature(
+    key: &VerifyingKey,
+    payload: &[u8],
+    signature: &str,
+    timestamp: u64,
+) -> Result<(), warp::Rejection> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+    if now.abs_diff(timestamp) > MAX_SIGNATURE_AGE_MS {
+        return Err(warp::reject::custom(UnauthorizedError("Signature expired")));
+    }
+
+    verify_signature(key, payload, signature)
+}
 
-    recv.recv().await.unwrap().map_err(warp::reject::custom)
+/// Get the message describing a rejection raised while handling a request
+fn rejection_message(err: &warp::Rejection) -> &'static str {
+    if let Some(BadRequestError(msg)) = err.find::<BadRequestError>() {
+        msg
+    } else if let Some(UnauthorizedError(msg)) = err.find::<UnauthorizedError>() {
+        msg
+    } else if let Some(ConflictError(msg)) = err.find::<ConflictError>() {
+        msg
+    } else if let Some(InternalError(msg)) = err.find::<InternalError>() {
+        msg
+    } else if err.find::<DealerSaturatedError>().is_some() {
+        "Dealer saturated"
+    } else {
+        "Internal error"
+    }
 }
 
 /// Handle a rejection from the dealer
-async fn handle_rejection(err: warp::Rejection) -> Result<impl warp::Reply, warp::Rejection> {
+async fn handle_rejection(err: warp::Rejection) -> Result<Response, warp::Rejection> {
     if let Some(BadRequestError(msg)) = err.find::<BadRequestError>() {
+        record_rejection("bad_request", msg);
         let json = warp::reply::json(&ErrorResponse { message: msg, code: 400 });
-        Ok(warp::reply::with_status(json, warp::http::StatusCode::BAD_REQUEST))
+        Ok(warp::reply::with_status(json, StatusCode::BAD_REQUEST).into_response())
     } else if let Some(UnauthorizedError(msg)) = err.find::<UnauthorizedError>() {
+        record_rejection("unauthorized", msg);
         let json = warp::reply::json(&ErrorResponse { message: msg, code: 401 });
-        Ok(warp::reply::with_status(json, warp::http::StatusCode::UNAUTHORIZED))
+        Ok(warp::reply::with_status(json, StatusCode::UNAUTHORIZED).into_response())
+    } else if let Some(NotFoundError(msg)) = err.find::<NotFoundError>() {
+        record_rejection("not_found", msg);
+        let json = warp::reply::json(&ErrorResponse { message: msg, code: 404 });
+        Ok(warp::reply::with_status(json, StatusCode::NOT_FOUND).into_response())
+    } else if let Some(ConflictError(msg)) = err.find::<ConflictError>() {
+        record_rejection("conflict", msg);
+        let json = warp::reply::json(&ErrorResponse { message: msg, code: 409 });
+        Ok(warp::reply::with_status(json, StatusCode::CONFLICT).into_response())
+    } else if let Some(InternalError(msg)) = err.find::<InternalError>() {
+        record_rejection("internal", msg);
+        let json = warp::reply::json(&ErrorResponse { message: msg, code: 500 });
+        Ok(warp::reply::with_status(json, StatusCode::INTERNAL_SERVER_ERROR).into_response())
+    } else if err.find::<DealerSaturatedError>().is_some() {
+        let msg = "Dealer saturated";
+        record_rejection("saturated", msg);
+        let json = warp::reply::json(&ErrorResponse { message: msg, code: 503 });
+        let reply = warp::reply::with_status(json, StatusCode::SERVICE_UNAVAILABLE);
+        Ok(warp::reply::with_header(reply, "Retry-After", RETRY_AFTER_SECS.to_string())
+            .into_response())
     } else {
         Err(err)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{net::SocketAddr, num::NonZeroUsize, time::Duration};
+
+    use ark_mpc::{
+        algebra::AuthenticatedScalarResult,
+        network::{MockNetwork, PartyId, UnboundedDuplexStream},
+        MpcFabric, PARTY0, PARTY1,
+    };
+    use base64::prelude::*;
+    use k256::ecdsa::{signature::Signer, Signature, SigningKey};
+    use rand::thread_rng;
+    use renegade_dealer::{dealer::create_dealer_sender_receiver, Dealer, DealerConfig};
+    use renegade_dealer_api::{
+        preprocessing::DealerPreprocessing, DealerRequest, DealerResponse, RequestId,
+        PARTY_ID_HEADER, SIGNATURE_HEADER,
+    };
+    use uuid::Uuid;
+
+    use super::{
+        enqueue_request, routes, validate_request, AsyncJobStore, BadRequestError, InternalError,
+    };
+
+    /// The curve used by the dealer
+    type Curve = ark_bn254::G1Projective;
+    /// A scalar over the dealer's curve
+    type Scalar = ark_mpc::algebra::Scalar<Curve>;
+
+    /// The number of random bits used in the test circuit
+    const N_BITS: usize = 8;
+
+    // -----------
+    // | Helpers |
+    // -----------
+
+    /// Start a server on an ephemeral local port, returning its address
+    fn start_server() -> SocketAddr {
+        let config = DealerConfig::default();
+        let (dealer_send, dealer_recv) = create_dealer_sender_receiver(config.queue_capacity);
+        let dealer = Dealer::start(config, dealer_recv);
+
+        let async_jobs = AsyncJobStore::new(Duration::from_secs(60), dealer_send.clone());
+        let ws_chunk_size = NonZeroUsize::new(10_000).unwrap();
+        let routes = routes(dealer_send, dealer, async_jobs, ws_chunk_size);
+        let (addr, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        addr
+    }
+
+    /// Sign and submit a request to the server as the given party
+    async fn submit(
+        addr: SocketAddr,
+        request_id: RequestId,
+        party_id: PartyId,
+        key: &SigningKey,
+        req: &DealerRequest,
+    ) -> DealerResponse {
+        let body = serde_json::to_vec(req).unwrap();
+        let payload = [request_id.to_bytes_le().as_ref(), &body].concat();
+        let sig: Signature = key.sign(&payload);
+
+        let resp = reqwest::Client::new()
+            .post(format!("http://{addr}/v0/offline-phase/{request_id}"))
+            .header(PARTY_ID_HEADER, party_id.to_string())
+            .header(SIGNATURE_HEADER, BASE64_STANDARD.encode(sig.to_bytes()))
+            .header("content-type", "application/json")
+            .body(body)
+            .send()
+            .await
+            .unwrap();
+
+        assert!(resp.status().is_success(), "request failed: {}", resp.status());
+        resp.json().await.unwrap()
+    }
+
+    /// Evaluate the test circuit in the given fabric
+    ///
+    /// Returns the opened values of `x * y` and of
+    /// `(x * y) * (x * y)^-1 + sum(b_i - b_i * b_i)`, which is one for shared
+    /// bits `b_i`
+    async fn circuit(fabric: &MpcFabric<Curve>, x: Scalar, y: Scalar) -> (Scalar, Scalar) {
+        let x = fabric.share_scalar(x, PARTY0);
+        let y = fabric.share_scalar(y, PARTY1);
+        let xy = &x * &y;
+        let xy_inv = xy.inverse();
+
+        let bits = fabric.random_shared_bits(N_BITS);
+        let bit_checks = bits.iter().map(|b| b - &(b * b));
+        let one = bit_checks.fold(&xy * &xy_inv, |acc, check| acc + check);
+
+        let xy_open = open(&xy).await;
+        let one_open = open(&one).await;
+        (xy_open, one_open)
+    }
+
+    /// Open an authenticated value, checking its MAC
+    async fn open(val: &AuthenticatedScalarResult<Curve>) -> Scalar {
+        val.open_authenticated().await.expect("MAC check failed")
+    }
+
+    // ---------
+    // | Tests |
+    // ---------
+
+    /// Tests a two party computation over preprocessing fetched from the server
+    #[tokio::test]
+    async fn test_end_to_end_mpc() {
+        let addr = start_server();
+
+        // Both parties sign and submit the same request
+        let mut rng = thread_rng();
+        let key1 = SigningKey::random(&mut rng);
+        let key2 = SigningKey::random(&mut rng);
+        let req = DealerRequest::new(key1.verifying_key().into(), key2.verifying_key().into())
+            .with_n_input_masks(1)
+            .with_n_random_bits(N_BITS as u32)
+            .with_n_inverse_pairs(1)
+            .with_n_triples(N_BITS as u32 + 3);
+
+        let rid = Uuid::new_v4();
+        let (resp1, resp2) = tokio::join!(
+            submit(addr, rid, PARTY0, &key1, &req),
+            submit(addr, rid, PARTY1, &key2, &req)
+        );
+
+        // Run the circuit in two fabrics over a mock network
+        let (stream1, stream2) = UnboundedDuplexStream::new_duplex_pair();
+        let fabric1 =
+            MpcFabric::new(MockNetwork::new(PARTY0, stream1), DealerPreprocessing::new(resp1));
+        let fabric2 =
+            MpcFabric::new(MockNetwork::new(PARTY1, stream2), DealerPreprocessing::new(resp2));
+
+        let x = Scalar::random(&mut rng);
+        let y = Scalar::random(&mut rng);
+        let (res1, res2) = tokio::join!(circuit(&fabric1, x, y), circuit(&fabric2, x, y));
+
+        assert_eq!(res1, res2);
+        assert_eq!(res1, (x * y, Scalar::one()));
+        fabric1.shutdown();
+        fabric2.shutdown();
+    }
+
+    /// Tests that a request signed by a client predating the optional counts
+    /// still verifies against the body the dealer re-serializes
+    #[test]
+    fn test_legacy_request_signature() {
+        let key = SigningKey::random(&mut thread_rng());
+        let req = DealerRequest::new(key.verifying_key().into(), key.verifying_key().into());
+        let keys = serde_json::to_value(&req).unwrap();
+
+        // The body as serialized by a client built before the optional counts
+        let body = format!(
+            "{{\"first_party_key\":{},\"second_party_key\":{},\"n_random_bits\":1,\
+             \"n_random_values\":2,\"n_input_masks\":3,\"n_inverse_pairs\":4,\"n_triples\":5}}",
+            keys["first_party_key"], keys["second_party_key"],
+        );
+        let rid = Uuid::new_v4();
+        let sig: Signature = key.sign(&[rid.to_bytes_le().as_ref(), body.as_bytes()].concat());
+
+        let req: DealerRequest = serde_json::from_str(&body).unwrap();
+        let sig = BASE64_STANDARD.encode(sig.to_bytes());
+        assert!(validate_request(rid, PARTY0, &sig, &req).is_ok());
+    }
+
+    /// Tests that a request is rejected rather than panicking when the dealer's
+    /// queue is closed
+    #[test]
+    fn test_closed_queue_rejected() {
+        let key = SigningKey::random(&mut thread_rng());
+        let req = DealerRequest::new(key.verifying_key().into(), key.verifying_key().into())
+            .with_n_triples(1);
+        let rid = Uuid::new_v4();
+        let body = serde_json::to_vec(&req).unwrap();
+        let sig: Signature = key.sign(&[rid.to_bytes_le().as_ref(), &body].concat());
+        let sig = BASE64_STANDARD.encode(sig.to_bytes());
+
+        let (dealer_send, dealer_recv) = create_dealer_sender_receiver(1 /* capacity */);
+        drop(dealer_recv);
+        let rej = enqueue_request(rid, PARTY0, &sig, req, &dealer_send).err().unwrap();
+        assert!(rej.find::<InternalError>().is_some());
+    }
+
+    /// Tests that a request whose counts overflow the total is rejected as too
+    /// large rather than wrapping around
+    #[test]
+    fn test_overflowing_counts_rejected() {
+        let key = SigningKey::random(&mut thread_rng());
+        let req = DealerRequest::new(key.verifying_key().into(), key.verifying_key().into())
+            .with_n_triples(u32::MAX)
+            .with_n_random_bits(1);
+        assert_eq!(req.total_values(), u32::MAX);
+
+        let rej = validate_request(Uuid::new_v4(), PARTY0, "", &req).unwrap_err();
+        assert!(rej.find::<BadRequestError>().is_some());
+    }
+}
@@ -0,0 +1,131 @@
+//! Prometheus metrics for the dealer
+//!
+//! All metrics are registered in the default registry and exported in the
+//! Prometheus text format on the `/metrics` endpoint
+
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram, register_int_counter, register_int_counter_vec, register_int_gauge,
+    Encoder, Histogram, IntCounter, IntCounterVec, IntGauge, TextEncoder,
+};
+use renegade_dealer_api::RequestCounts;
+
+/// The histogram buckets used for timing metrics, in seconds
+const TIMING_BUCKETS: &[f64] =
+    &[0.001, 0.005, 0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+lazy_static! {
+    /// The number of requests received by the server
+    pub static ref REQUESTS_RECEIVED: IntCounter =
+        register_int_counter!("dealer_requests_received_total", "Requests received by the dealer")
+            .unwrap();
+    /// The number of request pairs matched by the dealer
+    pub static ref PAIRINGS: IntCounter =
+        register_int_counter!("dealer_pairings_total", "Request pairs matched by the dealer")
+            .unwrap();
+    /// The number of rejected requests, labeled by error kind
+    pub static ref REJECTIONS: IntCounterVec = register_int_counter_vec!(
+        "dealer_rejections_total",
+        "Requests rejected by the dealer",
+        &["kind", "reason"]
+    )
+    .unwrap();
+    /// The number of values dealt, labeled by preprocessing type
+    pub static ref VALUES_DEALT: IntCounterVec = register_int_counter_vec!(
+        "dealer_values_dealt_total",
+        "Correlated values dealt to each party pair",
+        &["type"]
+    )
+    .unwrap();
+    /// The time a request waits for its counterparty
+    pub static ref PAIRING_WAIT_TIME: Histogram = register_histogram!(
+        "dealer_pairing_wait_seconds",
+        "Time a request waits in the dealer for its counterparty",
+        TIMING_BUCKETS.to_vec()
+    )
+    .unwrap();
+    /// The time taken to generate the correlated randomness for a pair
+    pub static ref GENERATION_TIME: Histogram = register_histogram!(
+        "dealer_generation_seconds",
+        "Time taken to generate the preprocessing for a pair of requests",
+        TIMING_BUCKETS.to_vec()
+    )
+    .unwrap();
+    /// The end to end latency of a request
+    pub static ref REQUEST_LATENCY: Histogram = register_histogram!(
+        "dealer_request_latency_seconds",
+        "End to end latency of a request to the dealer",
+        TIMING_BUCKETS.to_vec()
+    )
+    .unwrap();
+    /// The number of requests awaiting a counterparty
+    pub static ref OPEN_REQUESTS: IntGauge =
+        register_int_gauge!("dealer_open_requests", "Requests awaiting their counterparty")
+            .unwrap();
+    /// The number of open sessions
+    pub static ref OPEN_SESSIONS: IntGauge =
+        register_int_gauge!("dealer_open_sessions", "Sessions whose MAC key is held by the dealer")
+            .unwrap();
+    /// The number of pairs served from the preprocessing pool
+    pub static ref POOL_HITS: IntCounter =
+        register_int_counter!("dealer_pool_hits_total", "Pairs served from the preprocessing pool")
+            .unwrap();
+    /// The number of pairs of a pooled shape for which the preprocessing pool
+    /// had no batch ready
+    pub static ref POOL_MISSES: IntCounter = register_int_counter!(
+        "dealer_pool_misses_total",
+        "Pairs of a pooled shape for which the preprocessing pool had no batch ready"
+    )
+    .unwrap();
+    /// The number of batches ready in the preprocessing pool
+    pub static ref POOL_BATCHES: IntGauge =
+        register_int_gauge!("dealer_pool_batches", "Batches ready in the preprocessing pool")
+            .unwrap();
+    /// The number of jobs in the dealer's queue
+    pub static ref JOB_QUEUE_LENGTH: IntGauge =
+        register_int_gauge!("dealer_job_queue_length", "Jobs waiting in the dealer's queue")
+            .unwrap();
+}
+
+/// Record a rejected request
+pub fn record_rejection(kind: &str, reason: &str) {
+    REJECTIONS.with_label_values(&[kind, reason]).inc();
+}
+
+/// Record the values dealt for a request
+pub fn record_values_dealt(counts: &RequestCounts) {
+    let (first_party_masks, second_party_masks) = counts.party_input_masks();
+    let values = [
+        ("random_bits", counts.n_random_bits),
+        ("random_values", counts.n_random_values),
+        ("input_masks", first_party_masks.saturating_add(second_party_masks)),
+        ("inverse_pairs", counts.n_inverse_pairs),
+        ("triples", counts.n_triples),
+        ("square_pairs", counts.n_square_pairs),
+        ("truncation_pairs", counts.n_truncation_pairs),
+        ("edabits", counts.n_edabits),
+        ("power_tuples", counts.n_power_tuples),
+        ("prefix_product_tuples", counts.n_prefix_product_tuples),
+        ("inner_product_triples", counts.n_inner_product_triples),
+        ("matrix_triples", counts.n_matrix_triples),
+        ("zero_shares", counts.n_zero_shares),
+        ("point_shares", counts.n_point_shares),
+        ("binary_triples", counts.n_binary_triples),
+        ("ring_values", counts.n_ring_values()),
+        ("random_ots", counts.n_random_ots),
+        ("correlated_ots", counts.n_correlated_ots),
+    ];
+
+    for (ty, n) in values {
+        VALUES_DEALT.with_label_values(&[ty]).inc_by(n as u64);
+    }
+}
+
+/// Encode all registered metrics in the Prometheus text format
+pub fn encode_metrics() -> String {
+    let encoder = TextEncoder::new();
+    let mut buf = Vec::new();
+    encoder.encode(&prometheus::gather(), &mut buf).unwrap();
+
+    String::from_utf8(buf).unwrap()
+}
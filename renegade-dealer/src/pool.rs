@@ -0,0 +1,173 @@
+//! A pool of pre-generated preprocessing
+//!
+//! The pool holds batches of correlated randomness for a configured set of
+//! request shapes, each batch under its own MAC key. Background workers keep
+//! every shape filled to the configured depth, so a pair whose request matches
+//! a shape is served without waiting on generation
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use renegade_dealer_api::{DealerResponse, RequestCounts};
+use tokio::sync::Notify;
+use tracing::{debug, error, info};
+
+use crate::{
+    dealer::{sample_mac_key_shares, Dealer, MacKeyShares, MAX_REQUEST_SIZE},
+    metrics::{POOL_BATCHES, POOL_HITS, POOL_MISSES},
+};
+
+/// A batch of preprocessing for both parties, along with the MAC keys it was
+/// generated under
+type Batch = (DealerResponse, DealerResponse, MacKeyShares);
+
+/// The configuration of the preprocessing pool
+#[derive(Clone, Debug, Default)]
+pub struct PoolConfig {
+    /// The request shapes for which batches are pre-generated
+    pub shapes: Vec<RequestCounts>,
+    /// The number of batches kept ready for each shape
+    pub depth: usize,
+    /// The number of background workers generating batches
+    pub n_workers: usize,
+}
+
+impl PoolConfig {
+    /// Whether the configuration enables the pool
+    pub fn enabled(&self) -> bool {
+        !self.shapes.is_empty() && self.depth > 0 && self.n_workers > 0
+    }
+
+    /// Check that every shape is a well formed request within the size limit
+    pub fn validate(&self) -> Result<(), &'static str> {
+        for shape in &self.shapes {
+            if shape.total_values() > MAX_REQUEST_SIZE {
+                return Err("Pool shape too large");
+            }
+
+            shape.validate()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The batches of a single shape
+#[derive(Default)]
+struct ShapeBatches {
+    /// The batches ready to be served
+    ready: Vec<Batch>,
+    /// The number of batches being generated
+    in_flight: usize,
+}
+
+/// A pool of pre-generated preprocessing
+pub struct PreprocessingPool {
+    /// The batches of each shape
+    batches: Mutex<HashMap<RequestCounts, ShapeBatches>>,
+    /// The number of batches kept ready for each shape
+    depth: usize,
+    /// Notifies workers that a batch was taken from the pool
+    refill: Notify,
+}
+
+impl PreprocessingPool {
+    /// Create a pool and spawn its background workers
+    ///
+    /// Must be called from within a tokio runtime
+    pub fn start(config: &PoolConfig) -> Arc<Self> {
+        let batches = config.shapes.iter().map(|shape| (*shape, ShapeBatches::default())).collect();
+        let pool = Arc::new(Self {
+            batches: Mutex::new(batches),
+            depth: config.depth,
+            refill: Notify::new(),
+        });
+
+        info!(n_shapes = config.shapes.len(), depth = config.depth, "starting preprocessing pool");
+        for _ in 0..config.n_workers {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.run_worker().await });
+        }
+
+        pool
+    }
+
+    /// Take a batch of the given shape from the pool, if one is ready
+    ///
+    /// Only requests of a configured shape count towards the pool's hits and
+    /// misses
+    pub(crate) fn take(&self, counts: &RequestCounts) -> Option<Batch> {
+        let mut batches = self.batches.lock().unwrap();
+        let batch = batches.get_mut(counts)?.ready.pop();
+        if batch.is_some() {
+            POOL_HITS.inc();
+            POOL_BATCHES.dec();
+            self.refill.notify_waiters();
+        } else {
+            POOL_MISSES.inc();
+        }
+
+        batch
+    }
+
+    /// The number of batches of the given shape ready to be served
+    pub fn n_ready(&self, counts: &RequestCounts) -> usize {
+        self.batches.lock().unwrap().get(counts).map_or(0, |shape| shape.ready.len())
+    }
+
+    /// Run a background worker, generating batches until every shape is full
+    /// then waiting for batches to be taken
+    async fn run_worker(self: Arc<Self>) {
+        loop {
+            // Register for notifications before checking for work, so that a
+            // batch taken in between is not missed
+            let notified = self.refill.notified();
+            let Some(counts) = self.reserve_shape() else {
+                notified.await;
+                continue;
+            };
+
+            let res = tokio::task::spawn_blocking(move || {
+                let mac_key_shares = sample_mac_key_shares();
+                let (resp1, resp2) = Dealer::generate(&counts, mac_key_shares, |_| true)
+                    .expect("generation without cancellation");
+                (resp1, resp2, mac_key_shares)
+            })
+            .await;
+
+            // A shape whose generation panics is removed rather than retried, a
+            // batch of a removed shape is dropped
+            let mut batches = self.batches.lock().unwrap();
+            match res {
+                Ok(batch) => {
+                    let Some(shape) = batches.get_mut(&counts) else { continue };
+                    shape.in_flight -= 1;
+                    shape.ready.push(batch);
+                    POOL_BATCHES.inc();
+                },
+                Err(err) => {
+                    error!(shape = ?counts, "pool generation failed, removing shape: {err}");
+                    if let Some(shape) = batches.remove(&counts) {
+                        POOL_BATCHES.sub(shape.ready.len() as i64);
+                    }
+                    continue;
+                },
+            }
+
+            debug!("generated pool batch");
+        }
+    }
+
+    /// Reserve a shape that is below the pool's depth for generation
+    fn reserve_shape(&self) -> Option<RequestCounts> {
+        let mut batches = self.batches.lock().unwrap();
+        let (counts, shape) = batches
+            .iter_mut()
+            .find(|(_, shape)| shape.ready.len() + shape.in_flight < self.depth)?;
+
+        shape.in_flight += 1;
+        Some(*counts)
+    }
+}
@@ -0,0 +1,157 @@
+//! The websocket session protocol
+//!
+//! A session submits a single request to the dealer and streams the dealer's
+//! events back to the client, ending with the party's shares in chunks. See
+//! `renegade_dealer_api::ws` for the messages exchanged
+
+use std::num::NonZeroUsize;
+
+use ark_mpc::network::PartyId;
+use futures::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+use renegade_dealer::dealer::{DealerError, DealerEvent, DealerSender, ResponseReceiver};
+use renegade_dealer_api::{
+    ws::{ClientMessage, ServerMessage},
+    DealerResponse, RequestId,
+};
+use tracing::{field, info, instrument, warn, Span};
+use warp::ws::{Message, WebSocket};
+
+use crate::{cancel_request, enqueue_request, rejection_message};
+
+/// The sending half of a websocket
+type WsSink = SplitSink<WebSocket, Message>;
+/// The receiving half of a websocket
+type WsStream = SplitStream<WebSocket>;
+
+/// Handle a websocket session for the given request
+///
+/// The client's first message must submit the request. If the client cancels
+/// or disconnects before the session completes, the request is cancelled with
+/// the dealer
+#[instrument(name = "websocket_session", skip_all, fields(request_id = %request_id, party_id = field::Empty))]
+pub async fn handle_websocket(
+    socket: WebSocket,
+    request_id: RequestId,
+    dealer_queue: DealerSender,
+    chunk_size: NonZeroUsize,
+) {
+    let (mut sink, mut stream) = socket.split();
+    let (party_id, mut recv) = match await_submission(request_id, &mut stream, &dealer_queue).await
+    {
+        Ok(res) => res,
+        Err(message) => {
+            warn!("websocket submission rejected: {message}");
+            let _ = send_message(&mut sink, &ServerMessage::Error { message }).await;
+            return;
+        },
+    };
+    Span::current().record("party_id", party_id);
+
+    let cancel = || cancel_request(request_id, party_id, &dealer_queue);
+    if send_message(&mut sink, &ServerMessage::Accepted).await.is_err() {
+        cancel().await;
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            event = recv.recv() => {
+                let event = event.unwrap_or(Err(DealerError::Dropped));
+                let msg = match event {
+                    Ok(DealerEvent::Paired) => ServerMessage::CounterpartyJoined,
+                    Ok(DealerEvent::Progress { generated, total }) => {
+                        ServerMessage::Progress { generated, total }
+                    },
+                    Ok(DealerEvent::Response(resp)) => {
+                        if send_chunks(&mut sink, *resp, chunk_size).await.is_ok() {
+                            info!("websocket session complete");
+                        }
+                        return;
+                    },
+                    Err(DealerError::Cancelled) => {
+                        let _ = send_message(&mut sink, &ServerMessage::Cancelled).await;
+                        return;
+                    },
+                    Err(err) => {
+                        warn!("dealer rejected request: {err}");
+                        let message = err.to_string();
+                        let _ = send_message(&mut sink, &ServerMessage::Error { message }).await;
+                        return;
+                    },
+                };
+
+                if send_message(&mut sink, &msg).await.is_err() {
+                    cancel().await;
+                    return;
+                }
+            },
+            msg = stream.next() => match msg {
+                Some(Ok(msg)) if !msg.is_close() => {
+                    // Await the dealer's acknowledgement of the cancellation
+                    if let Some(ClientMessage::Cancel) = parse_message(&msg) {
+                        info!("client cancelled request");
+                        cancel().await;
+                    }
+                },
+                _ => {
+                    info!("client disconnected, cancelling request");
+                    cancel().await;
+                    return;
+                },
+            },
+        }
+    }
+}
+
+/// Await the client's submission and enqueue it with the dealer
+///
+/// Returns the submitting party and the channel on which the dealer sends the
+/// request's events, or a message describing why the submission was rejected
+async fn await_submission(
+    request_id: RequestId,
+    stream: &mut WsStream,
+    dealer_queue: &DealerSender,
+) -> Result<(PartyId, ResponseReceiver), String> {
+    let submission = match stream.next().await {
+        Some(Ok(msg)) => parse_message(&msg),
+        _ => None,
+    };
+
+    let Some(ClientMessage::Submit { party_id, signature, request }) = submission else {
+        return Err("Expected a submission".to_string());
+    };
+
+    let recv = enqueue_request(request_id, party_id, &signature, *request, dealer_queue)
+        .map_err(|rej| rejection_message(&rej).to_string())?;
+    Ok((party_id, recv))
+}
+
+/// Send a party's shares to the client in chunks, then close the session
+async fn send_chunks(
+    sink: &mut WsSink,
+    resp: DealerResponse,
+    chunk_size: NonZeroUsize,
+) -> Result<(), warp::Error> {
+    let chunks = resp.into_chunks(chunk_size.get());
+    let n_chunks = chunks.len() as u32;
+    for (index, response) in chunks.into_iter().enumerate() {
+        let (index, response) = (index as u32, Box::new(response));
+        send_message(sink, &ServerMessage::Chunk { index, n_chunks, response }).await?;
+    }
+
+    send_message(sink, &ServerMessage::Complete).await?;
+    sink.close().await
+}
+
+/// Parse a message from the client
+fn parse_message(msg: &Message) -> Option<ClientMessage> {
+    serde_json::from_str(msg.to_str().ok()?).ok()
+}
+
+/// Send a message to the client
+async fn send_message(sink: &mut WsSink, msg: &ServerMessage) -> Result<(), warp::Error> {
+    sink.send(Message::text(serde_json::to_string(msg).unwrap())).await
+}